@@ -2,7 +2,10 @@ use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use std::sync::{Arc, Mutex};
 use crossbeam_channel::Sender;
 use anyhow::Result;
-use crate::config::AudioConfig;
+use crate::config::{AudioConfig, RecordingMode};
+use crate::resampler::Resampler;
+use crate::segment::SegmentRecorder;
+use crate::muxer::{Fmp4Muxer, AUDIO_TRACK_ID};
 use crate::vad::VoiceActivityDetector;
 
 pub struct AudioRecorder {
@@ -12,20 +15,49 @@ pub struct AudioRecorder {
 }
 
 impl AudioRecorder {
-    pub fn new(config: AudioConfig, filepath_sender: Sender<String>) -> Result<Self> {
+    pub fn new(
+        config: AudioConfig,
+        filepath_sender: Sender<String>,
+        muxer: Option<Arc<Mutex<Fmp4Muxer>>>,
+    ) -> Result<Self> {
         let host = cpal::default_host();
-        let device = host.default_input_device()
-            .ok_or_else(|| anyhow::anyhow!("No input device found"))?;
+        let device = match &config.input_device_name {
+            Some(name) => host
+                .input_devices()?
+                .find(|d| d.name().map(|n| &n == name).unwrap_or(false))
+                .ok_or_else(|| anyhow::anyhow!("Input device '{}' not found", name))?,
+            None => host
+                .default_input_device()
+                .ok_or_else(|| anyhow::anyhow!("No input device found"))?,
+        };
 
         let default_config = device.default_input_config()?;
         let sample_rate = default_config.sample_rate().0;
-        let stream_config: cpal::StreamConfig = default_config.into();
+        let mut stream_config: cpal::StreamConfig = default_config.into();
+        stream_config.buffer_size =
+            cpal::BufferSize::Fixed(config.capture_period_frames as u32);
 
         println!("Native sample rate: {}Hz, resampling to 16kHz", sample_rate);
 
         let target_rate = config.target_sample_rate;
-        let resample_ratio = sample_rate as f64 / target_rate as f64;
-        let mut sample_buffer: Vec<f32> = Vec::new();
+        let chunk_size = config.chunk_size;
+        let mut resampler = Resampler::new(sample_rate, target_rate, config.resample_half_taps);
+        // Preallocate the accumulation buffer once at stream-open and reuse it
+        // for the life of the stream; incoming frames are appended here and full
+        // chunks are flushed out without reallocating per callback. The trailing
+        // partial period is kept across callbacks so no samples are dropped.
+        let capacity = config.capture_period_frames.max(chunk_size) + chunk_size;
+        let mut pending: Vec<i16> = Vec::with_capacity(capacity);
+        let mut scratch: Vec<i16> = Vec::with_capacity(chunk_size);
+
+        let mode = config.recording_mode.clone();
+        let run_vad = matches!(mode, RecordingMode::VadTriggered | RecordingMode::Both);
+        let mut segmenter = match mode {
+            RecordingMode::ContinuousSegmented | RecordingMode::Both => {
+                Some(SegmentRecorder::new(config.clone()))
+            }
+            RecordingMode::VadTriggered => None,
+        };
 
         // Initialize VAD
         let vad = Arc::new(Mutex::new(VoiceActivityDetector::new(config)));
@@ -37,38 +69,61 @@ impl AudioRecorder {
         let stream = device.build_input_stream(
             &stream_config,
             move |data: &[f32], _: &_| {
-                sample_buffer.extend_from_slice(data);
-
-                let required_samples = (resample_ratio * 512.0).ceil() as usize;
-
-                while sample_buffer.len() >= required_samples {
-                    let mut resampled = Vec::with_capacity(512);
+                // Band-limited resample to the target rate, then clamp to [-1,1]
+                // before the i16 conversion as before.
+                for sample in resampler.process(data) {
+                    pending.push((sample.clamp(-1.0, 1.0) * 32767.0) as i16);
+                }
 
-                    for i in 0..512 {
-                        let src_idx = (i as f64 * resample_ratio) as usize;
-                        if src_idx < sample_buffer.len() {
-                            resampled.push(sample_buffer[src_idx]);
+                while pending.len() >= chunk_size {
+                    // Copy a chunk into the reusable scratch buffer, then shift
+                    // the remainder down in place rather than allocating.
+                    scratch.clear();
+                    scratch.extend_from_slice(&pending[..chunk_size]);
+                    pending.copy_within(chunk_size.., 0);
+                    pending.truncate(pending.len() - chunk_size);
+
+                    // Feed the container muxer, if one is active, with this
+                    // chunk as one audio sample (little-endian i16 PCM).
+                    if let Some(muxer) = muxer.as_ref() {
+                        if let Ok(mut muxer) = muxer.lock() {
+                            let mut bytes = Vec::with_capacity(scratch.len() * 2);
+                            for &s in scratch.iter() {
+                                bytes.extend_from_slice(&s.to_le_bytes());
+                            }
+                            if let Err(e) =
+                                muxer.write_sample(AUDIO_TRACK_ID, &bytes, chunk_size as u32, true)
+                            {
+                                eprintln!("Muxer audio write failed: {}", e);
+                            }
                         }
                     }
 
-                    let consumed = (512.0 * resample_ratio) as usize;
-                    sample_buffer.drain(0..consumed.min(sample_buffer.len()));
-
-                    // Convert to i16 PCM
-                    let pcm_chunk: Vec<i16> = resampled
-                        .iter()
-                        .map(|&sample| (sample.clamp(-1.0, 1.0) * 32767.0) as i16)
-                        .collect();
-
-                    // Process through VAD
-                    if let Ok(mut vad) = vad_clone.lock() {
-                        if let Some(filepath) = vad.process_chunk(pcm_chunk) {
-                            // Speech segment completed, send filepath
+                    // Continuous segmentation, independent of voice activity.
+                    if let Some(segmenter) = segmenter.as_mut() {
+                        if let Some(filepath) = segmenter.process_chunk(&scratch) {
                             if let Err(e) = sender_clone.send(filepath) {
                                 eprintln!("Failed to send filepath: {}", e);
                             }
                         }
                     }
+
+                    if let Ok(mut vad) = vad_clone.lock() {
+                        // Feed the HDF5 session's PCM timeline independent of
+                        // recording mode, so continuous-segmented-only setups
+                        // don't silently lose audio from the session file.
+                        vad.feed_session_pcm(&scratch);
+
+                        // VAD-triggered utterance capture.
+                        if run_vad {
+                            if let Some(filepath) = vad.process_chunk(&scratch) {
+                                // Speech segment completed, send filepath
+                                if let Err(e) = sender_clone.send(filepath) {
+                                    eprintln!("Failed to send filepath: {}", e);
+                                }
+                            }
+                        }
+                    }
                 }
             },
             err_fn,
@@ -82,6 +137,18 @@ impl AudioRecorder {
         })
     }
 
+    /// Attach an HDF5 session recorder so the VAD feeds it the PCM timeline and
+    /// per-utterance events.
+    #[cfg(feature = "hdf5")]
+    pub fn set_session(
+        &self,
+        session: Arc<Mutex<crate::hdf5_recorder::Hdf5Recorder>>,
+    ) {
+        if let Ok(mut vad) = self.vad.lock() {
+            vad.set_session(session);
+        }
+    }
+
     pub fn start(&self) -> Result<()> {
         if let Some(ref stream) = self.stream {
             stream.play()?;
@@ -0,0 +1,168 @@
+//! Assemble the JPEG frame buffer into a single playable Motion-JPEG AVI clip.
+//!
+//! The frames are independently JPEG-encoded already, so we only need to wrap
+//! them in an AVI RIFF container: an `hdrl` header describing one MJPG video
+//! stream, a `movi` list of `00dc` chunks carrying the JPEG payloads, and an
+//! `idx1` index. The container bytes are written directly, the same way
+//! `save_wav` hand-writes its RIFF header, so no extra dependency is pulled in.
+
+use anyhow::Result;
+use std::fs;
+use std::path::PathBuf;
+
+fn push_fourcc(buf: &mut Vec<u8>, tag: &[u8; 4]) {
+    buf.extend_from_slice(tag);
+}
+
+fn push_u16(buf: &mut Vec<u8>, v: u16) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+fn push_u32(buf: &mut Vec<u8>, v: u32) {
+    buf.extend_from_slice(&v.to_le_bytes());
+}
+
+/// Write the frames (each a JPEG with a presentation offset in seconds from the
+/// first frame) to `output_path` as an MJPEG AVI, returning the path. The header
+/// frame rate is derived from the actual inter-frame intervals.
+pub fn write_mjpeg_avi(
+    frames: &[(f32, Vec<u8>)],
+    width: u32,
+    height: u32,
+    output_path: &PathBuf,
+) -> Result<String> {
+    if frames.is_empty() {
+        return Err(anyhow::anyhow!("No frames in the requested window"));
+    }
+
+    let num_frames = frames.len() as u32;
+    // Average frame rate over the span, kept as `rate / scale` frames per second
+    // so sub-1 fps capture keeps its timing; fall back to 1 fps for a single frame.
+    let span = frames.last().map(|(t, _)| *t).unwrap_or(0.0);
+    let scale = 1000u32;
+    let rate = if span > 0.0 && num_frames > 1 {
+        ((num_frames - 1) as f32 / span * scale as f32).round().max(1.0) as u32
+    } else {
+        scale
+    };
+    let micros_per_frame = (1_000_000u64 * scale as u64 / rate as u64) as u32;
+    let max_chunk = frames.iter().map(|(_, d)| d.len()).max().unwrap_or(0) as u32;
+
+    // Build the movi payload and collect index entries as we go. Offsets are
+    // relative to the `movi` FOURCC (so the first chunk lands at offset 4).
+    let mut movi = Vec::new();
+    let mut index: Vec<(u32, u32)> = Vec::with_capacity(frames.len());
+    for (_, data) in frames {
+        let offset = movi.len() as u32 + 4;
+        push_fourcc(&mut movi, b"00dc");
+        push_u32(&mut movi, data.len() as u32);
+        movi.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            movi.push(0); // chunks are word-aligned
+        }
+        index.push((offset, data.len() as u32));
+    }
+
+    // hdrl: avih + strl(strh + strf)
+    let mut hdrl = Vec::new();
+    push_fourcc(&mut hdrl, b"hdrl");
+
+    // avih
+    push_fourcc(&mut hdrl, b"avih");
+    push_u32(&mut hdrl, 56);
+    push_u32(&mut hdrl, micros_per_frame);
+    push_u32(&mut hdrl, (max_chunk as u64 * rate as u64 / scale as u64) as u32); // dwMaxBytesPerSec (approx)
+    push_u32(&mut hdrl, 0); // dwPaddingGranularity
+    push_u32(&mut hdrl, 0x10); // dwFlags = AVIF_HASINDEX
+    push_u32(&mut hdrl, num_frames);
+    push_u32(&mut hdrl, 0); // dwInitialFrames
+    push_u32(&mut hdrl, 1); // dwStreams
+    push_u32(&mut hdrl, max_chunk);
+    push_u32(&mut hdrl, width);
+    push_u32(&mut hdrl, height);
+    for _ in 0..4 {
+        push_u32(&mut hdrl, 0); // dwReserved[4]
+    }
+
+    // strl
+    let mut strl = Vec::new();
+    push_fourcc(&mut strl, b"strl");
+    // strh
+    push_fourcc(&mut strl, b"strh");
+    push_u32(&mut strl, 56);
+    push_fourcc(&mut strl, b"vids");
+    push_fourcc(&mut strl, b"MJPG");
+    push_u32(&mut strl, 0); // dwFlags
+    push_u16(&mut strl, 0); // wPriority
+    push_u16(&mut strl, 0); // wLanguage
+    push_u32(&mut strl, 0); // dwInitialFrames
+    push_u32(&mut strl, scale); // dwScale
+    push_u32(&mut strl, rate); // dwRate
+    push_u32(&mut strl, 0); // dwStart
+    push_u32(&mut strl, num_frames); // dwLength
+    push_u32(&mut strl, max_chunk); // dwSuggestedBufferSize
+    push_u32(&mut strl, 0xFFFF_FFFF); // dwQuality (-1 = default)
+    push_u32(&mut strl, 0); // dwSampleSize
+    push_u16(&mut strl, 0); // rcFrame.left
+    push_u16(&mut strl, 0); // rcFrame.top
+    push_u16(&mut strl, width as u16); // rcFrame.right
+    push_u16(&mut strl, height as u16); // rcFrame.bottom
+    // strf (BITMAPINFOHEADER)
+    push_fourcc(&mut strl, b"strf");
+    push_u32(&mut strl, 40);
+    push_u32(&mut strl, 40); // biSize
+    push_u32(&mut strl, width); // biWidth
+    push_u32(&mut strl, height); // biHeight
+    push_u16(&mut strl, 1); // biPlanes
+    push_u16(&mut strl, 24); // biBitCount
+    push_fourcc(&mut strl, b"MJPG"); // biCompression
+    push_u32(&mut strl, width * height * 3); // biSizeImage
+    push_u32(&mut strl, 0); // biXPelsPerMeter
+    push_u32(&mut strl, 0); // biYPelsPerMeter
+    push_u32(&mut strl, 0); // biClrUsed
+    push_u32(&mut strl, 0); // biClrImportant
+
+    // Wrap strl as a LIST inside hdrl.
+    push_fourcc(&mut hdrl, b"LIST");
+    push_u32(&mut hdrl, strl.len() as u32);
+    hdrl.extend_from_slice(&strl);
+
+    // idx1
+    let mut idx1 = Vec::new();
+    for (offset, len) in &index {
+        push_fourcc(&mut idx1, b"00dc");
+        push_u32(&mut idx1, 0x10); // AVIIF_KEYFRAME
+        push_u32(&mut idx1, *offset);
+        push_u32(&mut idx1, *len);
+    }
+
+    // Assemble the file.
+    let mut out = Vec::new();
+    push_fourcc(&mut out, b"RIFF");
+    let riff_size_pos = out.len();
+    push_u32(&mut out, 0); // patched below
+    push_fourcc(&mut out, b"AVI ");
+
+    // hdrl LIST
+    push_fourcc(&mut out, b"LIST");
+    push_u32(&mut out, hdrl.len() as u32);
+    out.extend_from_slice(&hdrl);
+
+    // movi LIST
+    push_fourcc(&mut out, b"LIST");
+    push_u32(&mut out, (movi.len() + 4) as u32);
+    push_fourcc(&mut out, b"movi");
+    out.extend_from_slice(&movi);
+
+    // idx1 chunk
+    push_fourcc(&mut out, b"idx1");
+    push_u32(&mut out, idx1.len() as u32);
+    out.extend_from_slice(&idx1);
+
+    // Patch the RIFF size (everything after the size field itself).
+    let riff_size = (out.len() - riff_size_pos - 4) as u32;
+    out[riff_size_pos..riff_size_pos + 4].copy_from_slice(&riff_size.to_le_bytes());
+
+    fs::write(output_path, &out)?;
+    Ok(output_path.to_string_lossy().to_string())
+}
@@ -4,6 +4,11 @@ use std::collections::HashMap;
 pub enum AudioFormat {
     Wav,
     Flac,
+    /// Synchronized session container written by the `hdf5`-gated backend.
+    Hdf5,
+    Aac,
+    Opus,
+    Mp3,
 }
 
 impl Default for AudioFormat {
@@ -12,12 +17,36 @@ impl Default for AudioFormat {
     }
 }
 
+/// How the audio subsystem decides what to write to disk.
+#[derive(Clone, Debug, PartialEq)]
+pub enum RecordingMode {
+    /// Only emit a file when the VAD detects a speech utterance (the default).
+    VadTriggered,
+    /// Always write rolling fixed-duration segments, ignoring voice activity.
+    ContinuousSegmented,
+    /// Run both the VAD and the continuous segmenter at the same time.
+    Both,
+}
+
+impl Default for RecordingMode {
+    fn default() -> Self {
+        RecordingMode::VadTriggered
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct AudioConfig {
     pub target_sample_rate: u32,
     pub chunk_size: usize,
+    pub capture_period_frames: usize,
     pub format: AudioFormat,
     pub flac_compression: u8,
+    pub aac_bitrate_kbps: u32,
+    pub opus_bitrate_kbps: u32,
+    pub mp3_bitrate_kbps: u32,
+    pub resample_half_taps: usize,
+    pub recording_mode: RecordingMode,
+    pub seconds_per_segment: f32,
     pub spike_factor: f32,
     pub stop_factor: f32,
     pub release_ratio: f32,
@@ -26,6 +55,9 @@ pub struct AudioConfig {
     pub min_record_seconds: f32,
     pub background_alpha: f32,
     pub output_directory: String,
+    /// Explicit cpal input device name (as surfaced by `list_input_devices`);
+    /// `None` falls back to the host default input device.
+    pub input_device_name: Option<String>,
 }
 
 impl Default for AudioConfig {
@@ -33,8 +65,15 @@ impl Default for AudioConfig {
         AudioConfig {
             target_sample_rate: 16000,
             chunk_size: 512,
+            capture_period_frames: 2048,
             format: AudioFormat::Flac,
             flac_compression: 5,
+            aac_bitrate_kbps: 64,
+            opus_bitrate_kbps: 32,
+            mp3_bitrate_kbps: 64,
+            resample_half_taps: 16,
+            recording_mode: RecordingMode::VadTriggered,
+            seconds_per_segment: 5.0,
             spike_factor: 2.5,
             stop_factor: 2.5,
             release_ratio: 0.25,
@@ -43,10 +82,26 @@ impl Default for AudioConfig {
             min_record_seconds: 0.3,
             background_alpha: 0.95,
             output_directory: "data/recordings".to_string(),
+            input_device_name: None,
         }
     }
 }
 
+/// Codec the video capture path encodes frames with.
+#[derive(Clone, Debug, PartialEq)]
+pub enum VideoCodec {
+    /// Independent JPEG frames (the default, stored directly in the buffer).
+    Mjpeg,
+    H264,
+    Av1,
+}
+
+impl Default for VideoCodec {
+    fn default() -> Self {
+        VideoCodec::Mjpeg
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct VideoConfig {
     pub enabled: bool,
@@ -56,6 +111,12 @@ pub struct VideoConfig {
     pub height: u32,
     pub jpeg_quality: u8,
     pub buffer_duration_secs: f32,
+    pub scene_change_threshold: f32,
+    pub keyframe_max_interval_secs: f32,
+    pub video_codec: VideoCodec,
+    pub hardware_accel: bool,
+    pub video_quality: u8,
+    pub video_bitrate: u32,
 }
 
 impl Default for VideoConfig {
@@ -68,14 +129,41 @@ impl Default for VideoConfig {
             height: 480,
             jpeg_quality: 75,
             buffer_duration_secs: 30.0,
+            scene_change_threshold: 8.0,
+            keyframe_max_interval_secs: 10.0,
+            video_codec: VideoCodec::Mjpeg,
+            hardware_accel: false,
+            video_quality: 75,
+            video_bitrate: 2_000_000,
         }
     }
 }
 
+/// Output container that muxes the audio take and video frames together.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ContainerFormat {
+    /// Keep audio and video as separate outputs (the default).
+    None,
+    /// Mux both tracks into a single fragmented MP4.
+    FragmentedMp4,
+    // FLV live/incremental delivery is only available as the standalone
+    // `FlvStreamSink` Python class (see `sink::FlvSink`) driven directly by
+    // the caller, not as a `RecorderConfig` container: nothing in
+    // `NativeRecorder::new` feeds a file-backed FLV muxer, so a "flv"
+    // container option here would silently record nothing.
+}
+
+impl Default for ContainerFormat {
+    fn default() -> Self {
+        ContainerFormat::None
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct RecorderConfig {
     pub audio: AudioConfig,
     pub video: VideoConfig,
+    pub container: ContainerFormat,
 }
 
 impl Default for RecorderConfig {
@@ -83,6 +171,7 @@ impl Default for RecorderConfig {
         RecorderConfig {
             audio: AudioConfig::default(),
             video: VideoConfig::default(),
+            container: ContainerFormat::None,
         }
     }
 }
@@ -97,15 +186,44 @@ impl RecorderConfig {
         if let Some(ConfigValue::Float(val)) = dict.get("chunk_size") {
             config.audio.chunk_size = *val as usize;
         }
+        if let Some(ConfigValue::Float(val)) = dict.get("capture_period_frames") {
+            config.audio.capture_period_frames = (*val as usize).max(1);
+        }
         if let Some(ConfigValue::String(val)) = dict.get("audio_format") {
             config.audio.format = match val.as_str() {
                 "wav" => AudioFormat::Wav,
+                "hdf5" => AudioFormat::Hdf5,
+                "aac" => AudioFormat::Aac,
+                "opus" => AudioFormat::Opus,
+                "mp3" => AudioFormat::Mp3,
                 _ => AudioFormat::Flac,
             };
         }
         if let Some(ConfigValue::Float(val)) = dict.get("flac_compression") {
             config.audio.flac_compression = (*val as u8).min(8);
         }
+        if let Some(ConfigValue::Float(val)) = dict.get("aac_bitrate_kbps") {
+            config.audio.aac_bitrate_kbps = *val as u32;
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("opus_bitrate_kbps") {
+            config.audio.opus_bitrate_kbps = *val as u32;
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("mp3_bitrate_kbps") {
+            config.audio.mp3_bitrate_kbps = *val as u32;
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("resample_half_taps") {
+            config.audio.resample_half_taps = (*val as usize).max(1);
+        }
+        if let Some(ConfigValue::String(val)) = dict.get("recording_mode") {
+            config.audio.recording_mode = match val.as_str() {
+                "continuous_segmented" => RecordingMode::ContinuousSegmented,
+                "both" => RecordingMode::Both,
+                _ => RecordingMode::VadTriggered,
+            };
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("seconds_per_segment") {
+            config.audio.seconds_per_segment = *val as f32;
+        }
         if let Some(ConfigValue::Float(val)) = dict.get("spike_factor") {
             config.audio.spike_factor = *val as f32;
         }
@@ -130,6 +248,9 @@ impl RecorderConfig {
         if let Some(ConfigValue::String(val)) = dict.get("output_directory") {
             config.audio.output_directory = val.clone();
         }
+        if let Some(ConfigValue::String(val)) = dict.get("input_device_name") {
+            config.audio.input_device_name = Some(val.clone());
+        }
 
         if let Some(ConfigValue::Bool(val)) = dict.get("video_enabled") {
             config.video.enabled = *val;
@@ -152,6 +273,36 @@ impl RecorderConfig {
         if let Some(ConfigValue::Float(val)) = dict.get("buffer_duration_secs") {
             config.video.buffer_duration_secs = *val as f32;
         }
+        if let Some(ConfigValue::Float(val)) = dict.get("scene_change_threshold") {
+            config.video.scene_change_threshold = *val as f32;
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("keyframe_max_interval_secs") {
+            config.video.keyframe_max_interval_secs = *val as f32;
+        }
+
+        if let Some(ConfigValue::String(val)) = dict.get("video_codec") {
+            config.video.video_codec = match val.as_str() {
+                "h264" => VideoCodec::H264,
+                "av1" => VideoCodec::Av1,
+                _ => VideoCodec::Mjpeg,
+            };
+        }
+        if let Some(ConfigValue::Bool(val)) = dict.get("hardware_accel") {
+            config.video.hardware_accel = *val;
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("video_quality") {
+            config.video.video_quality = (*val as u8).min(100);
+        }
+        if let Some(ConfigValue::Float(val)) = dict.get("video_bitrate") {
+            config.video.video_bitrate = *val as u32;
+        }
+
+        if let Some(ConfigValue::String(val)) = dict.get("container") {
+            config.container = match val.as_str() {
+                "fragmented_mp4" | "fmp4" => ContainerFormat::FragmentedMp4,
+                _ => ContainerFormat::None,
+            };
+        }
 
         config
     }
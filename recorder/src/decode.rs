@@ -0,0 +1,206 @@
+//! Read a finished recording back into interleaved i16 PCM for review or
+//! re-processing (e.g. resampling to `target_sample_rate` or running a local
+//! VAD re-pass). Dispatch is by file extension: `.flac`, `.wav`, `.ogg`,
+//! `.opus` and `.mp3` are decoded; `.aac` takes and `.h5` session files are
+//! not, and return an error.
+
+use anyhow::Result;
+use std::path::Path;
+
+/// Decoded PCM plus the format it was stored in.
+pub struct DecodedAudio {
+    pub samples: Vec<i16>,
+    pub sample_rate: u32,
+    pub channels: u16,
+}
+
+/// Decode `path` into PCM, choosing the decoder from the file extension.
+pub fn decode_to_pcm<P: AsRef<Path>>(path: P) -> Result<DecodedAudio> {
+    let path = path.as_ref();
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase())
+        .unwrap_or_default();
+
+    match ext.as_str() {
+        "flac" => decode_flac(path),
+        "wav" => decode_wav(path),
+        "ogg" => decode_ogg(path),
+        "opus" => decode_opus(path),
+        "mp3" => decode_mp3(path),
+        other => Err(anyhow::anyhow!("Unsupported extension for decode: {}", other)),
+    }
+}
+
+/// Decode a WAV file written by `vad::save_wav`: a fixed 44-byte
+/// RIFF/WAVE/fmt /data header (PCM, mono, 16-bit) followed by raw i16 LE
+/// samples.
+fn decode_wav(path: &Path) -> Result<DecodedAudio> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut header = [0u8; 44];
+    file.read_exact(&mut header)?;
+
+    if &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(anyhow::anyhow!("Not a RIFF/WAVE file"));
+    }
+    let channels = u16::from_le_bytes([header[22], header[23]]);
+    let sample_rate = u32::from_le_bytes([header[24], header[25], header[26], header[27]]);
+    let bits_per_sample = u16::from_le_bytes([header[34], header[35]]);
+    if bits_per_sample != 16 {
+        return Err(anyhow::anyhow!(
+            "Unsupported WAV bit depth: {}",
+            bits_per_sample
+        ));
+    }
+
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    let samples = data
+        .chunks_exact(2)
+        .map(|b| i16::from_le_bytes([b[0], b[1]]))
+        .collect();
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+fn decode_flac(path: &Path) -> Result<DecodedAudio> {
+    let mut reader = claxon::FlacReader::open(path)?;
+    let info = reader.streaminfo();
+    let sample_rate = info.sample_rate;
+    let channels = info.channels as u16;
+
+    let mut samples = Vec::new();
+    for sample in reader.samples() {
+        samples.push(sample? as i16);
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+fn decode_ogg(path: &Path) -> Result<DecodedAudio> {
+    use lewton::inside_ogg::OggStreamReader;
+    use std::fs::File;
+
+    let file = File::open(path)?;
+    let mut reader = OggStreamReader::new(file)?;
+    let sample_rate = reader.ident_hdr.audio_sample_rate;
+    let channels = reader.ident_hdr.audio_channels as u16;
+
+    let mut samples = Vec::new();
+    while let Some(packet) = reader.read_dec_packet_itl()? {
+        samples.extend_from_slice(&packet);
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+/// Decode an Ogg-Opus file written by `vad::save_opus`: read the `OpusHead`
+/// identification header for the channel count and input sample rate, skip the
+/// `OpusTags` comment header, then decode every audio packet.
+fn decode_opus(path: &Path) -> Result<DecodedAudio> {
+    use ogg::PacketReader;
+    use opus::{Channels, Decoder};
+    use std::fs::File;
+
+    let file = File::open(path)?;
+    let mut reader = PacketReader::new(file);
+
+    let mut sample_rate = 48000u32;
+    let mut channels = 1u16;
+    let mut decoder: Option<Decoder> = None;
+    let mut samples = Vec::new();
+    let mut packet_idx = 0usize;
+
+    while let Some(packet) = reader
+        .read_packet()
+        .map_err(|e| anyhow::anyhow!("Ogg read failed: {:?}", e))?
+    {
+        match packet_idx {
+            0 => {
+                // OpusHead: channel count at byte 9, input sample rate at 12..16.
+                let data = &packet.data;
+                if data.len() >= 16 {
+                    channels = data[9] as u16;
+                    sample_rate =
+                        u32::from_le_bytes([data[12], data[13], data[14], data[15]]);
+                }
+                let ch = if channels >= 2 {
+                    Channels::Stereo
+                } else {
+                    Channels::Mono
+                };
+                decoder = Some(
+                    Decoder::new(sample_rate, ch)
+                        .map_err(|e| anyhow::anyhow!("Opus decoder init failed: {:?}", e))?,
+                );
+            }
+            1 => {} // OpusTags comment header, nothing we need
+            _ => {
+                let dec = decoder
+                    .as_mut()
+                    .ok_or_else(|| anyhow::anyhow!("Opus stream missing header"))?;
+                // 120 ms at 48 kHz is the largest frame Opus can produce.
+                let mut out = vec![0i16; 5760 * channels as usize];
+                let decoded = dec
+                    .decode(&packet.data, &mut out, false)
+                    .map_err(|e| anyhow::anyhow!("Opus decode failed: {:?}", e))?;
+                samples.extend_from_slice(&out[..decoded * channels as usize]);
+            }
+        }
+        packet_idx += 1;
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
+
+fn decode_mp3(path: &Path) -> Result<DecodedAudio> {
+    use minimp3::{Decoder, Error as Mp3Error, Frame};
+
+    let mut decoder = Decoder::new(std::fs::File::open(path)?);
+    let mut samples = Vec::new();
+    let mut sample_rate = 0;
+    let mut channels = 0u16;
+
+    // Concatenate every decoded frame; the first frame establishes the format.
+    loop {
+        match decoder.next_frame() {
+            Ok(Frame {
+                data,
+                sample_rate: sr,
+                channels: ch,
+                ..
+            }) => {
+                sample_rate = sr as u32;
+                channels = ch as u16;
+                samples.extend_from_slice(&data);
+            }
+            Err(Mp3Error::Eof) => break,
+            Err(e) => return Err(anyhow::anyhow!("MP3 decode failed: {:?}", e)),
+        }
+    }
+
+    Ok(DecodedAudio {
+        samples,
+        sample_rate,
+        channels,
+    })
+}
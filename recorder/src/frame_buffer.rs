@@ -2,8 +2,20 @@ use parking_lot::RwLock;
 use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
+/// A single buffered JPEG frame together with the metadata the scene-change
+/// detector produces for it.
+struct Frame {
+    captured: Instant,
+    data: Vec<u8>,
+    /// Whether this frame was stored as a keyframe (scene change or the
+    /// periodic interval) rather than an ordinary interval frame.
+    is_keyframe: bool,
+    /// Mean-absolute-difference score against the previously stored frame.
+    change_score: f32,
+}
+
 pub struct FrameBuffer {
-    frames: RwLock<VecDeque<(Instant, Vec<u8>)>>,
+    frames: RwLock<VecDeque<Frame>>,
     max_duration: Duration,
     max_frames: usize,
 }
@@ -18,18 +30,23 @@ impl FrameBuffer {
         }
     }
 
-    pub fn push(&self, jpeg_data: Vec<u8>) {
+    pub fn push(&self, jpeg_data: Vec<u8>, is_keyframe: bool, change_score: f32) {
         let mut frames = self.frames.write();
         let now = Instant::now();
 
-        frames.push_back((now, jpeg_data));
+        frames.push_back(Frame {
+            captured: now,
+            data: jpeg_data,
+            is_keyframe,
+            change_score,
+        });
 
         while frames.len() > self.max_frames {
             frames.pop_front();
         }
 
         let cutoff = now.checked_sub(self.max_duration).unwrap_or(now);
-        while frames.front().map(|(t, _)| *t < cutoff).unwrap_or(false) {
+        while frames.front().map(|f| f.captured < cutoff).unwrap_or(false) {
             frames.pop_front();
         }
     }
@@ -40,21 +57,65 @@ impl FrameBuffer {
 
         frames
             .iter()
-            .filter(|(t, _)| *t >= cutoff)
-            .map(|(_, data)| data.clone())
+            .filter(|f| f.captured >= cutoff)
+            .map(|f| f.data.clone())
+            .collect()
+    }
+
+    /// Like [`get_frames_since`] but returns only the visually distinct frames
+    /// (scene changes and periodic keyframes), so downstream vision calls skip
+    /// near-identical duplicates.
+    pub fn get_keyframes_since(&self, duration_secs: f32) -> Vec<Vec<u8>> {
+        let frames = self.frames.read();
+        let cutoff = Instant::now().checked_sub(Duration::from_secs_f32(duration_secs)).unwrap_or(Instant::now());
+
+        frames
+            .iter()
+            .filter(|f| f.is_keyframe && f.captured >= cutoff)
+            .map(|f| f.data.clone())
+            .collect()
+    }
+
+    /// Return the frames captured within a window that starts `start_secs_ago`
+    /// seconds before now and spans `duration_secs` forward from there, paired
+    /// with each frame's offset in seconds from the first returned frame. Used
+    /// to assemble a clip with correct inter-frame timing, e.g. the frames
+    /// covering an utterance that started `start_secs_ago` ago and ran for
+    /// `duration_secs`.
+    pub fn frames_in_window(&self, start_secs_ago: f32, duration_secs: f32) -> Vec<(f32, Vec<u8>)> {
+        let frames = self.frames.read();
+        let now = Instant::now();
+        let start = now
+            .checked_sub(Duration::from_secs_f32(start_secs_ago.max(0.0)))
+            .unwrap_or(now);
+        let end = start + Duration::from_secs_f32(duration_secs.max(0.0));
+
+        let selected: Vec<&Frame> = frames
+            .iter()
+            .filter(|f| f.captured >= start && f.captured <= end)
+            .collect();
+
+        let first = match selected.first() {
+            Some(f) => f.captured,
+            None => return Vec::new(),
+        };
+
+        selected
+            .iter()
+            .map(|f| (f.captured.duration_since(first).as_secs_f32(), f.data.clone()))
             .collect()
     }
 
     pub fn get_latest(&self) -> Option<Vec<u8>> {
-        self.frames.read().back().map(|(_, data)| data.clone())
+        self.frames.read().back().map(|f| f.data.clone())
     }
 
     pub fn stats(&self) -> (usize, f32) {
         let frames = self.frames.read();
         let count = frames.len();
         let duration = if count > 1 {
-            if let (Some((first, _)), Some((last, _))) = (frames.front(), frames.back()) {
-                last.duration_since(*first).as_secs_f32()
+            if let (Some(first), Some(last)) = (frames.front(), frames.back()) {
+                last.captured.duration_since(first.captured).as_secs_f32()
             } else {
                 0.0
             }
@@ -0,0 +1,164 @@
+//! Synchronized session-recording backend that ties the audio take, the camera
+//! frames captured during it, and timing metadata together in a single,
+//! self-describing `.h5` container instead of scattered per-utterance files.
+//!
+//! Gated behind the `hdf5` Cargo feature so the dependency is optional.
+
+use crate::config::AudioConfig;
+use anyhow::Result;
+use hdf5::types::VarLenArray;
+use hdf5::File;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Number of PCM samples per chunk in the extendable audio dataset.
+const PCM_CHUNK: usize = 16384;
+
+/// One `.h5` file per recording session.
+///
+/// Raw i16 PCM lives in a chunked, extendable `pcm` dataset; JPEG frames live in
+/// a variable-length `frames` dataset with their capture offsets (seconds from
+/// the session start) in a parallel `frame_offsets` dataset; and each finalized
+/// VAD segment adds a row to the `events` group describing its sample range and
+/// the frame indices that fall within it.
+pub struct Hdf5Recorder {
+    file: File,
+    path: String,
+    sample_rate: u32,
+    /// Session clock; frame offsets and sample offsets share this origin.
+    start: Instant,
+    pcm_len: usize,
+    frame_count: usize,
+    event_count: usize,
+    /// In-memory mirror of the capture offset (seconds from `start`) of every
+    /// stored frame, used to map a sample range to the frames within it.
+    frame_offsets: Vec<f64>,
+}
+
+impl Hdf5Recorder {
+    /// Open a new session file named by the start timestamp and stamp the
+    /// top-level attributes from the supplied configuration.
+    pub fn new(config: &AudioConfig, width: u32, height: u32, fps: f32) -> Result<Self> {
+        std::fs::create_dir_all(&config.output_directory)?;
+
+        let timestamp = chrono::Local::now().format("%y%m%d_%H%M%S").to_string();
+        let filepath = PathBuf::from(&config.output_directory).join(format!("{}.h5", timestamp));
+        let path = filepath.to_string_lossy().to_string();
+
+        let file = File::create(&filepath)?;
+
+        file.new_dataset::<i16>()
+            .chunk(PCM_CHUNK)
+            .shape(0..)
+            .create("pcm")?;
+        file.new_dataset::<VarLenArray<u8>>()
+            .chunk(1)
+            .shape(0..)
+            .create("frames")?;
+        file.new_dataset::<f64>()
+            .chunk(64)
+            .shape(0..)
+            .create("frame_offsets")?;
+        file.create_group("events")?;
+
+        file.new_attr::<u32>()
+            .create("sample_rate")?
+            .write_scalar(&config.target_sample_rate)?;
+        file.new_attr::<u32>()
+            .create("camera_width")?
+            .write_scalar(&width)?;
+        file.new_attr::<u32>()
+            .create("camera_height")?
+            .write_scalar(&height)?;
+        file.new_attr::<f32>()
+            .create("camera_fps")?
+            .write_scalar(&fps)?;
+        let start = chrono::Local::now().to_rfc3339();
+        file.new_attr::<VarLenArray<u8>>()
+            .create("start_timestamp")?
+            .write_scalar(&VarLenArray::from_slice(start.as_bytes()))?;
+
+        Ok(Self {
+            file,
+            path,
+            sample_rate: config.target_sample_rate,
+            start: Instant::now(),
+            pcm_len: 0,
+            frame_count: 0,
+            event_count: 0,
+            frame_offsets: Vec::new(),
+        })
+    }
+
+    /// Append PCM samples to the audio dataset and return the running total
+    /// sample count (i.e. the sample offset of the next write).
+    pub fn append_pcm(&mut self, samples: &[i16]) -> Result<usize> {
+        let ds = self.file.dataset("pcm")?;
+        let new_len = self.pcm_len + samples.len();
+        ds.resize(new_len)?;
+        ds.write_slice(samples, self.pcm_len..new_len)?;
+        self.pcm_len = new_len;
+        Ok(self.pcm_len)
+    }
+
+    /// Append a JPEG frame, stamping it with its capture offset in seconds from
+    /// the session start, and return the frame's index.
+    pub fn append_frame(&mut self, jpeg: &[u8]) -> Result<usize> {
+        let idx = self.frame_count;
+        let offset_secs = self.start.elapsed().as_secs_f64();
+
+        let frames = self.file.dataset("frames")?;
+        frames.resize(idx + 1)?;
+        frames.write_slice(&[VarLenArray::from_slice(jpeg)], idx..idx + 1)?;
+
+        let offsets = self.file.dataset("frame_offsets")?;
+        offsets.resize(idx + 1)?;
+        offsets.write_slice(&[offset_secs], idx..idx + 1)?;
+
+        self.frame_offsets.push(offset_secs);
+        self.frame_count += 1;
+        Ok(idx)
+    }
+
+    /// Record a finalized speech segment from its start/end sample offsets,
+    /// computing and storing the indices of the frames that fall within the
+    /// window so a consumer can reconstruct the accompanying video.
+    pub fn append_event(&mut self, start_sample: usize, end_sample: usize) -> Result<()> {
+        let sr = self.sample_rate as f64;
+        let start_secs = start_sample as f64 / sr;
+        let end_secs = end_sample as f64 / sr;
+        let frame_indices: Vec<usize> = self
+            .frame_offsets
+            .iter()
+            .enumerate()
+            .filter(|(_, &o)| o >= start_secs && o <= end_secs)
+            .map(|(i, _)| i)
+            .collect();
+
+        let events = self.file.group("events")?;
+        let group = events.create_group(&format!("event_{:05}", self.event_count))?;
+
+        group
+            .new_attr::<u64>()
+            .create("start_sample")?
+            .write_scalar(&(start_sample as u64))?;
+        group
+            .new_attr::<u64>()
+            .create("end_sample")?
+            .write_scalar(&(end_sample as u64))?;
+
+        let indices: Vec<u64> = frame_indices.iter().map(|&i| i as u64).collect();
+        group
+            .new_dataset::<u64>()
+            .shape(indices.len())
+            .create("frame_indices")?
+            .write(&indices)?;
+
+        self.event_count += 1;
+        Ok(())
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+}
@@ -1,13 +1,22 @@
 use crossbeam_channel::{unbounded, Receiver};
 use pyo3::prelude::*;
-use pyo3::types::PyDict;
+use pyo3::types::{PyDict, PyList};
 use std::collections::HashMap;
 
 mod audio;
+mod clip;
 mod config;
+mod decode;
 mod frame_buffer;
+#[cfg(feature = "hdf5")]
+mod hdf5_recorder;
+mod muxer;
+mod resampler;
+mod segment;
+mod sink;
 mod vad;
 mod video;
+mod video_encoder;
 
 use audio::AudioRecorder;
 use config::{ConfigValue, RecorderConfig};
@@ -19,6 +28,10 @@ struct NativeRecorder {
     video: Option<VideoRecorder>,
     filepath_rx: Receiver<String>,
     config: RecorderConfig,
+    /// Active container muxer when `container = fragmented_mp4`.
+    muxer: Option<std::sync::Arc<std::sync::Mutex<muxer::Fmp4Muxer>>>,
+    #[cfg(feature = "hdf5")]
+    session: Option<std::sync::Arc<std::sync::Mutex<hdf5_recorder::Hdf5Recorder>>>,
 }
 
 #[pymethods]
@@ -30,10 +43,33 @@ impl NativeRecorder {
 
         let (filepath_tx, filepath_rx) = unbounded();
 
-        let audio = AudioRecorder::new(config.audio.clone(), filepath_tx)
+        // Spin up the container muxer up front so both capture paths can feed it.
+        let muxer = if config.container == config::ContainerFormat::FragmentedMp4 {
+            std::fs::create_dir_all(&config.audio.output_directory)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            let timestamp = chrono::Local::now().format("%y%m%d_%H%M%S").to_string();
+            let path = std::path::PathBuf::from(&config.audio.output_directory)
+                .join(format!("{}.mp4", timestamp));
+            let mut m = muxer::Fmp4Muxer::new(
+                &path,
+                config.audio.target_sample_rate,
+                1,
+                config.video.width as u16,
+                config.video.height as u16,
+            )
             .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            m.write_header(b"isom", &[*b"isom", *b"iso5", *b"mp41"])
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            Some(std::sync::Arc::new(std::sync::Mutex::new(m)))
+        } else {
+            None
+        };
 
-        let video = if config.video.enabled {
+        let audio = AudioRecorder::new(config.audio.clone(), filepath_tx, muxer.clone())
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+
+        #[allow(unused_mut)]
+        let mut video = if config.video.enabled {
             Some(
                 VideoRecorder::new(config.video.clone())
                     .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?,
@@ -42,11 +78,40 @@ impl NativeRecorder {
             None
         };
 
+        if let (Some(video), Some(muxer)) = (video.as_mut(), muxer.as_ref()) {
+            video.set_muxer(muxer.clone());
+        }
+
+        #[cfg(feature = "hdf5")]
+        let session = if config.audio.format == config::AudioFormat::Hdf5 {
+            let recorder = hdf5_recorder::Hdf5Recorder::new(
+                &config.audio,
+                config.video.width,
+                config.video.height,
+                config.video.fps,
+            )
+            .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            let handle = std::sync::Arc::new(std::sync::Mutex::new(recorder));
+
+            // Feed the session recorder from both capture paths.
+            audio.set_session(handle.clone());
+            if let Some(video) = video.as_mut() {
+                video.set_session(handle.clone());
+            }
+
+            Some(handle)
+        } else {
+            None
+        };
+
         Ok(NativeRecorder {
             audio: Some(audio),
             video,
             filepath_rx,
             config,
+            muxer,
+            #[cfg(feature = "hdf5")]
+            session,
         })
     }
 
@@ -73,6 +138,13 @@ impl NativeRecorder {
         if let Some(video) = &mut self.video {
             video.stop();
         }
+        if let Some(muxer) = &self.muxer {
+            if let Ok(mut muxer) = muxer.lock() {
+                muxer
+                    .write_end()
+                    .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+            }
+        }
         Ok(())
     }
 
@@ -97,6 +169,20 @@ impl NativeRecorder {
             .collect())
     }
 
+    fn get_keyframes_for_duration(&self, py: Python, duration_secs: f32) -> PyResult<Vec<PyObject>> {
+        use pyo3::types::PyBytes;
+
+        let frames = match &self.video {
+            Some(v) => v.get_keyframes_for_duration(duration_secs),
+            None => vec![],
+        };
+
+        Ok(frames
+            .into_iter()
+            .map(|data| PyBytes::new(py, &data).into())
+            .collect())
+    }
+
     fn get_latest_frame(&self, py: Python) -> PyResult<Option<PyObject>> {
         use pyo3::types::PyBytes;
 
@@ -106,6 +192,24 @@ impl NativeRecorder {
         }
     }
 
+    fn export_clip_for_event(
+        &self,
+        start_offset_secs: f32,
+        duration_secs: f32,
+    ) -> PyResult<Option<String>> {
+        match &self.video {
+            Some(v) => v
+                .export_clip(
+                    start_offset_secs,
+                    duration_secs,
+                    &self.config.audio.output_directory,
+                )
+                .map(Some)
+                .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string())),
+            None => Ok(None),
+        }
+    }
+
     fn get_buffer_stats(&self) -> PyResult<(usize, f32)> {
         match &self.video {
             Some(v) => Ok(v.stats()),
@@ -117,8 +221,203 @@ impl NativeRecorder {
         Ok(match self.config.audio.format {
             config::AudioFormat::Flac => "flac".to_string(),
             config::AudioFormat::Wav => "wav".to_string(),
+            config::AudioFormat::Hdf5 => "hdf5".to_string(),
+            config::AudioFormat::Aac => "aac".to_string(),
+            config::AudioFormat::Opus => "opus".to_string(),
+            config::AudioFormat::Mp3 => "mp3".to_string(),
         })
     }
+
+    /// Path of the active HDF5 session container, if the `hdf5` feature is
+    /// compiled in and the recorder was constructed in HDF5 session mode.
+    fn get_session_path(&self) -> PyResult<Option<String>> {
+        #[cfg(feature = "hdf5")]
+        {
+            return Ok(self
+                .session
+                .as_ref()
+                .and_then(|s| s.lock().ok().map(|s| s.path().to_string())));
+        }
+        #[cfg(not(feature = "hdf5"))]
+        {
+            Ok(None)
+        }
+    }
+
+    fn get_recording_mode(&self) -> PyResult<String> {
+        Ok(match self.config.audio.recording_mode {
+            config::RecordingMode::VadTriggered => "vad_triggered".to_string(),
+            config::RecordingMode::ContinuousSegmented => "continuous_segmented".to_string(),
+            config::RecordingMode::Both => "both".to_string(),
+        })
+    }
+
+    fn get_segment_length(&self) -> PyResult<f32> {
+        Ok(self.config.audio.seconds_per_segment)
+    }
+}
+
+/// Streaming FLV writer exposed to Python for live push: construct it, send the
+/// `header()` bytes once, then forward the buffer returned by each
+/// `write_audio`/`write_video` call over the wire as coded samples arrive.
+#[pyclass]
+struct FlvStreamSink {
+    inner: sink::FlvSink,
+}
+
+#[pymethods]
+impl FlvStreamSink {
+    #[new]
+    fn new(has_audio: bool, has_video: bool) -> Self {
+        FlvStreamSink {
+            inner: sink::FlvSink::new(has_audio, has_video),
+        }
+    }
+
+    fn header(&mut self, py: Python) -> PyObject {
+        use pyo3::types::PyBytes;
+        use sink::RecorderSink;
+        PyBytes::new(py, &self.inner.header()).into()
+    }
+
+    fn write_audio(
+        &mut self,
+        py: Python,
+        data: &[u8],
+        timestamp_ms: u32,
+        is_sequence_header: bool,
+    ) -> PyObject {
+        use pyo3::types::PyBytes;
+        use sink::RecorderSink;
+        PyBytes::new(py, &self.inner.write_audio(data, timestamp_ms, is_sequence_header)).into()
+    }
+
+    fn write_video(
+        &mut self,
+        py: Python,
+        data: &[u8],
+        timestamp_ms: u32,
+        is_keyframe: bool,
+        is_sequence_header: bool,
+    ) -> PyObject {
+        use pyo3::types::PyBytes;
+        use sink::RecorderSink;
+        PyBytes::new(
+            py,
+            &self
+                .inner
+                .write_video(data, timestamp_ms, is_keyframe, is_sequence_header),
+        )
+        .into()
+    }
+}
+
+/// Enumerate the available cpal input devices and their supported configs, so a
+/// Python caller can pick and validate a device before constructing the
+/// recorder. Each entry is a dict with `name`, `default` and a `configs` list of
+/// `{channels, min_sample_rate, max_sample_rate}` dicts.
+#[pyfunction]
+fn list_input_devices(py: Python) -> PyResult<Vec<PyObject>> {
+    use cpal::traits::{DeviceTrait, HostTrait};
+
+    let host = cpal::default_host();
+    let default_name = host
+        .default_input_device()
+        .and_then(|d| d.name().ok());
+
+    let devices = host
+        .input_devices()
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+
+    let mut out = Vec::new();
+    for device in devices {
+        let name = device.name().unwrap_or_else(|_| "unknown".to_string());
+
+        let configs = PyList::empty(py);
+        if let Ok(supported) = device.supported_input_configs() {
+            for cfg in supported {
+                let entry = PyDict::new(py);
+                entry.set_item("channels", cfg.channels())?;
+                entry.set_item("min_sample_rate", cfg.min_sample_rate().0)?;
+                entry.set_item("max_sample_rate", cfg.max_sample_rate().0)?;
+                configs.append(entry)?;
+            }
+        }
+
+        let dict = PyDict::new(py);
+        dict.set_item("name", &name)?;
+        dict.set_item("default", Some(&name) == default_name.as_ref())?;
+        dict.set_item("configs", configs)?;
+        out.push(dict.into());
+    }
+
+    Ok(out)
+}
+
+/// Enumerate the available cameras and, where the backend can report them, their
+/// supported `(resolution, format, fps)` tuples. Each entry is a dict with
+/// `index`, `name` and a `formats` list of `{width, height, format, fps}` dicts.
+#[pyfunction]
+fn list_cameras(py: Python) -> PyResult<Vec<PyObject>> {
+    use nokhwa::pixel_format::RgbFormat;
+    use nokhwa::query;
+    use nokhwa::utils::{ApiBackend, RequestedFormat, RequestedFormatType};
+    use nokhwa::Camera;
+
+    let cameras = query(ApiBackend::Auto)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+
+    let mut out = Vec::new();
+    for info in cameras {
+        let dict = PyDict::new(py);
+        dict.set_item("index", info.index().to_string())?;
+        dict.set_item("name", info.human_name())?;
+        dict.set_item("description", info.description())?;
+
+        // Open the device to ask the backend which formats it supports. This is
+        // best-effort: a busy or unreadable device leaves `formats` empty rather
+        // than failing the whole enumeration.
+        let formats = PyList::empty(py);
+        let requested =
+            RequestedFormat::new::<RgbFormat>(RequestedFormatType::AbsoluteHighestFrameRate);
+        if let Ok(mut camera) = Camera::new(info.index().clone(), requested) {
+            if let Ok(mut supported) = camera.compatible_camera_formats() {
+                // Stable ordering: highest resolution first, then frame rate.
+                supported.sort_by(|a, b| {
+                    (b.resolution().width_x * b.resolution().height_y)
+                        .cmp(&(a.resolution().width_x * a.resolution().height_y))
+                        .then(b.frame_rate().cmp(&a.frame_rate()))
+                });
+                for fmt in supported {
+                    let entry = PyDict::new(py);
+                    entry.set_item("width", fmt.resolution().width_x)?;
+                    entry.set_item("height", fmt.resolution().height_y)?;
+                    entry.set_item("format", format!("{}", fmt.format()))?;
+                    entry.set_item("fps", fmt.frame_rate())?;
+                    formats.append(entry)?;
+                }
+            }
+        }
+        dict.set_item("formats", formats)?;
+        out.push(dict.into());
+    }
+
+    Ok(out)
+}
+
+/// Decode a finished recording back into PCM. Returns a dict with `samples`
+/// (a list of i16), `sample_rate` and `channels`, dispatched on the file
+/// extension.
+#[pyfunction]
+fn decode_to_pcm(py: Python, path: String) -> PyResult<PyObject> {
+    let decoded = decode::decode_to_pcm(&path)
+        .map_err(|e| PyErr::new::<pyo3::exceptions::PyRuntimeError, _>(e.to_string()))?;
+
+    let dict = PyDict::new(py);
+    dict.set_item("samples", decoded.samples)?;
+    dict.set_item("sample_rate", decoded.sample_rate)?;
+    dict.set_item("channels", decoded.channels)?;
+    Ok(dict.into())
 }
 
 fn parse_python_dict(py_dict: &PyDict) -> PyResult<HashMap<String, ConfigValue>> {
@@ -142,5 +441,9 @@ fn parse_python_dict(py_dict: &PyDict) -> PyResult<HashMap<String, ConfigValue>>
 #[pymodule]
 fn recorder(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<NativeRecorder>()?;
+    m.add_class::<FlvStreamSink>()?;
+    m.add_function(wrap_pyfunction!(list_input_devices, m)?)?;
+    m.add_function(wrap_pyfunction!(list_cameras, m)?)?;
+    m.add_function(wrap_pyfunction!(decode_to_pcm, m)?)?;
     Ok(())
 }
@@ -0,0 +1,385 @@
+//! Fragmented-MP4 muxer combining the recorded audio track and the buffered
+//! video JPEG frames into a single file written incrementally.
+//!
+//! The file opens with `ftyp` and a `moov` whose sample tables are empty and
+//! whose `mvex` advertises the fragments to come; each [`Fmp4Muxer::write_sample`]
+//! then appends one self-contained `moof`+`mdat` fragment. Because every
+//! fragment is valid on its own, the file stays playable even if recording is
+//! killed mid-take.
+
+use anyhow::Result;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+pub const AUDIO_TRACK_ID: u32 = 1;
+pub const VIDEO_TRACK_ID: u32 = 2;
+
+/// Per-track bookkeeping for the running fragment timeline.
+struct Track {
+    id: u32,
+    timescale: u32,
+    /// Accumulated decode time, in track timescale units.
+    base_media_decode_time: u64,
+}
+
+pub struct Fmp4Muxer {
+    writer: BufWriter<File>,
+    audio: Track,
+    video: Track,
+    audio_sample_rate: u32,
+    audio_channels: u16,
+    video_width: u16,
+    video_height: u16,
+    sequence_number: u32,
+}
+
+/// Wrap `payload` in a box with the given four-character `typ`.
+fn mp4_box(typ: &[u8; 4], payload: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(payload.len() + 8);
+    out.extend_from_slice(&((payload.len() as u32 + 8).to_be_bytes()));
+    out.extend_from_slice(typ);
+    out.extend_from_slice(payload);
+    out
+}
+
+/// Wrap `payload` in a full box, prepending the version/flags word.
+fn full_box(typ: &[u8; 4], version: u8, flags: u32, payload: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(payload.len() + 4);
+    body.push(version);
+    body.extend_from_slice(&flags.to_be_bytes()[1..]); // 24-bit flags
+    body.extend_from_slice(payload);
+    mp4_box(typ, &body)
+}
+
+impl Fmp4Muxer {
+    pub fn new(
+        filepath: &PathBuf,
+        audio_sample_rate: u32,
+        audio_channels: u16,
+        video_width: u16,
+        video_height: u16,
+    ) -> Result<Self> {
+        let file = File::create(filepath)?;
+        Ok(Self {
+            writer: BufWriter::new(file),
+            audio: Track {
+                id: AUDIO_TRACK_ID,
+                timescale: audio_sample_rate,
+                base_media_decode_time: 0,
+            },
+            video: Track {
+                id: VIDEO_TRACK_ID,
+                timescale: 1000,
+                base_media_decode_time: 0,
+            },
+            audio_sample_rate,
+            audio_channels,
+            video_width,
+            video_height,
+            sequence_number: 0,
+        })
+    }
+
+    /// Write `ftyp` and the initial `moov` with empty sample tables and an
+    /// `mvex` that declares the upcoming fragments.
+    pub fn write_header(&mut self, major_brand: &[u8; 4], compatible_brands: &[[u8; 4]]) -> Result<()> {
+        // ftyp
+        let mut ftyp = Vec::new();
+        ftyp.extend_from_slice(major_brand);
+        ftyp.extend_from_slice(&0x200u32.to_be_bytes()); // minor version
+        for brand in compatible_brands {
+            ftyp.extend_from_slice(brand);
+        }
+        self.writer.write_all(&mp4_box(b"ftyp", &ftyp))?;
+
+        // moov = mvhd + trak(audio) + trak(video) + mvex
+        let mut moov = Vec::new();
+        moov.extend_from_slice(&self.mvhd());
+        moov.extend_from_slice(&self.trak_audio());
+        moov.extend_from_slice(&self.trak_video());
+        moov.extend_from_slice(&self.mvex());
+        self.writer.write_all(&mp4_box(b"moov", &moov))?;
+
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Append one `moof`+`mdat` fragment carrying a single sample for the given
+    /// track. `duration` is in the track's timescale; `is_sync` marks a sync
+    /// (key) sample.
+    pub fn write_sample(
+        &mut self,
+        track_id: u32,
+        data: &[u8],
+        duration: u32,
+        is_sync: bool,
+    ) -> Result<()> {
+        self.sequence_number += 1;
+        let seq = self.sequence_number;
+
+        let base_decode_time = {
+            let track = self.track_mut(track_id);
+            let t = track.base_media_decode_time;
+            track.base_media_decode_time += duration as u64;
+            t
+        };
+
+        // traf = tfhd + tfdt + trun
+        let mut tfhd = Vec::new();
+        tfhd.extend_from_slice(&track_id.to_be_bytes());
+        // flags 0x020000 = default-base-is-moof
+        let tfhd = full_box(b"tfhd", 0, 0x020000, &tfhd);
+
+        let mut tfdt = Vec::new();
+        tfdt.extend_from_slice(&base_decode_time.to_be_bytes());
+        let tfdt = full_box(b"tfdt", 1, 0, &tfdt);
+
+        // trun: sample-count, data-offset, then per-sample duration/size/flags.
+        // flags 0x000301 | 0x000400 = data-offset + sample-duration +
+        // sample-size + sample-flags; the sample-flags bit must be set or
+        // parsers ignore the flags word we append below and the is_sync bit
+        // never reaches the output.
+        let sample_flags: u32 = if is_sync { 0x0200_0000 } else { 0x0001_0000 };
+        let mut trun_body = Vec::new();
+        trun_body.extend_from_slice(&1u32.to_be_bytes()); // sample_count
+        let data_offset_pos = trun_body.len();
+        trun_body.extend_from_slice(&0u32.to_be_bytes()); // data_offset, patched below
+        trun_body.extend_from_slice(&duration.to_be_bytes());
+        trun_body.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        trun_body.extend_from_slice(&sample_flags.to_be_bytes());
+        let trun = full_box(b"trun", 0, 0x000301 | 0x000400, &trun_body);
+
+        let mfhd = full_box(b"mfhd", 0, 0, &seq.to_be_bytes());
+
+        let mut traf = Vec::new();
+        traf.extend_from_slice(&tfhd);
+        traf.extend_from_slice(&tfdt);
+        traf.extend_from_slice(&trun);
+        let traf = mp4_box(b"traf", &traf);
+
+        let mut moof_body = Vec::new();
+        moof_body.extend_from_slice(&mfhd);
+        moof_body.extend_from_slice(&traf);
+        let mut moof = mp4_box(b"moof", &moof_body);
+
+        // data_offset in trun is relative to the start of the moof box and must
+        // point at the first byte of the mdat payload.
+        let data_offset = moof.len() as u32 + 8; // + mdat header
+        // Locate the trun's data_offset field inside the assembled moof: moof
+        // header(8) + mfhd + traf header(8) + tfhd + tfdt + trun header(12 = box
+        // header 8 + version/flags 4) + sample_count(4), i.e. `data_offset_pos`.
+        let patch_at = 8 + mfhd.len() + 8 + tfhd.len() + tfdt.len() + 12 + data_offset_pos;
+        moof[patch_at..patch_at + 4].copy_from_slice(&data_offset.to_be_bytes());
+
+        self.writer.write_all(&moof)?;
+        self.writer.write_all(&mp4_box(b"mdat", data))?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Finish the file. Fragmented MP4 needs no trailing box for playability, so
+    /// this just flushes any buffered bytes.
+    pub fn write_end(&mut self) -> Result<()> {
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    fn track_mut(&mut self, track_id: u32) -> &mut Track {
+        if track_id == AUDIO_TRACK_ID {
+            &mut self.audio
+        } else {
+            &mut self.video
+        }
+    }
+
+    fn mvhd(&self) -> Vec<u8> {
+        let mut p = Vec::new();
+        p.extend_from_slice(&0u32.to_be_bytes()); // creation_time
+        p.extend_from_slice(&0u32.to_be_bytes()); // modification_time
+        p.extend_from_slice(&1000u32.to_be_bytes()); // timescale
+        p.extend_from_slice(&0u32.to_be_bytes()); // duration (unknown for fmp4)
+        p.extend_from_slice(&0x0001_0000u32.to_be_bytes()); // rate 1.0
+        p.extend_from_slice(&0x0100u16.to_be_bytes()); // volume 1.0
+        p.extend_from_slice(&[0u8; 10]); // reserved
+        p.extend_from_slice(&unity_matrix());
+        p.extend_from_slice(&[0u8; 24]); // predefined
+        p.extend_from_slice(&(VIDEO_TRACK_ID + 1).to_be_bytes()); // next_track_ID
+        full_box(b"mvhd", 0, 0, &p)
+    }
+
+    fn trak_audio(&self) -> Vec<u8> {
+        let minf = {
+            let smhd = full_box(b"smhd", 0, 0, &[0u8; 4]);
+            let stbl = empty_stbl_audio(self.audio_sample_rate, self.audio_channels);
+            let mut body = Vec::new();
+            body.extend_from_slice(&smhd);
+            body.extend_from_slice(&dinf());
+            body.extend_from_slice(&stbl);
+            mp4_box(b"minf", &body)
+        };
+        trak(self.audio.id, self.audio.timescale, b"soun", 0, 0, &minf)
+    }
+
+    fn trak_video(&self) -> Vec<u8> {
+        let minf = {
+            let vmhd = full_box(b"vmhd", 0, 1, &[0u8; 8]);
+            let stbl = empty_stbl_video(self.video_width, self.video_height);
+            let mut body = Vec::new();
+            body.extend_from_slice(&vmhd);
+            body.extend_from_slice(&dinf());
+            body.extend_from_slice(&stbl);
+            mp4_box(b"minf", &body)
+        };
+        trak(
+            self.video.id,
+            self.video.timescale,
+            b"vide",
+            self.video_width,
+            self.video_height,
+            &minf,
+        )
+    }
+
+    fn mvex(&self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for id in [self.audio.id, self.video.id] {
+            let mut trex = Vec::new();
+            trex.extend_from_slice(&id.to_be_bytes());
+            trex.extend_from_slice(&1u32.to_be_bytes()); // default_sample_description_index
+            trex.extend_from_slice(&0u32.to_be_bytes()); // default_sample_duration
+            trex.extend_from_slice(&0u32.to_be_bytes()); // default_sample_size
+            trex.extend_from_slice(&0u32.to_be_bytes()); // default_sample_flags
+            body.extend_from_slice(&full_box(b"trex", 0, 0, &trex));
+        }
+        mp4_box(b"mvex", &body)
+    }
+}
+
+fn unity_matrix() -> [u8; 36] {
+    let mut m = [0u8; 36];
+    m[0..4].copy_from_slice(&0x0001_0000u32.to_be_bytes()); // a
+    m[16..20].copy_from_slice(&0x0001_0000u32.to_be_bytes()); // d
+    m[32..36].copy_from_slice(&0x4000_0000u32.to_be_bytes()); // w
+    m
+}
+
+fn dinf() -> Vec<u8> {
+    // dref with a single self-contained url entry.
+    let url = full_box(b"url ", 0, 1, &[]);
+    let mut dref = Vec::new();
+    dref.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+    dref.extend_from_slice(&url);
+    let dref = full_box(b"dref", 0, 0, &dref);
+    mp4_box(b"dinf", &dref)
+}
+
+fn empty_sample_tables(stsd: &[u8]) -> Vec<u8> {
+    let mut body = Vec::new();
+    body.extend_from_slice(stsd);
+    body.extend_from_slice(&full_box(b"stts", 0, 0, &0u32.to_be_bytes()));
+    body.extend_from_slice(&full_box(b"stsc", 0, 0, &0u32.to_be_bytes()));
+    // stsz: sample_size=0, sample_count=0
+    body.extend_from_slice(&full_box(b"stsz", 0, 0, &[0u8; 8]));
+    body.extend_from_slice(&full_box(b"stco", 0, 0, &0u32.to_be_bytes()));
+    mp4_box(b"stbl", &body)
+}
+
+fn empty_stbl_audio(sample_rate: u32, channels: u16) -> Vec<u8> {
+    // mp4a sample entry (decoder config is omitted here; the fragments carry raw
+    // coded frames for the container subsystem to reference).
+    let mut mp4a = Vec::new();
+    mp4a.extend_from_slice(&[0u8; 6]); // reserved
+    mp4a.extend_from_slice(&1u16.to_be_bytes()); // data_reference_index
+    mp4a.extend_from_slice(&[0u8; 8]); // reserved
+    mp4a.extend_from_slice(&channels.to_be_bytes());
+    mp4a.extend_from_slice(&16u16.to_be_bytes()); // sample size
+    mp4a.extend_from_slice(&[0u8; 4]); // predefined + reserved
+    mp4a.extend_from_slice(&(sample_rate << 16).to_be_bytes());
+    let mp4a = mp4_box(b"mp4a", &mp4a);
+
+    let mut stsd = Vec::new();
+    stsd.extend_from_slice(&1u32.to_be_bytes()); // entry_count
+    stsd.extend_from_slice(&mp4a);
+    let stsd = full_box(b"stsd", 0, 0, &stsd);
+    empty_sample_tables(&stsd)
+}
+
+fn empty_stbl_video(width: u16, height: u16) -> Vec<u8> {
+    // mjpg sample entry for the buffered JPEG frames.
+    let mut mjpg = Vec::new();
+    mjpg.extend_from_slice(&[0u8; 6]); // reserved
+    mjpg.extend_from_slice(&1u16.to_be_bytes()); // data_reference_index
+    mjpg.extend_from_slice(&[0u8; 16]); // predefined/reserved
+    mjpg.extend_from_slice(&width.to_be_bytes());
+    mjpg.extend_from_slice(&height.to_be_bytes());
+    mjpg.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // horiz resolution 72dpi
+    mjpg.extend_from_slice(&0x0048_0000u32.to_be_bytes()); // vert resolution 72dpi
+    mjpg.extend_from_slice(&0u32.to_be_bytes()); // reserved
+    mjpg.extend_from_slice(&1u16.to_be_bytes()); // frame_count
+    mjpg.extend_from_slice(&[0u8; 32]); // compressor name
+    mjpg.extend_from_slice(&24u16.to_be_bytes()); // depth
+    mjpg.extend_from_slice(&0xFFFFu16.to_be_bytes()); // predefined -1
+    let mjpg = mp4_box(b"mjpg", &mjpg);
+
+    let mut stsd = Vec::new();
+    stsd.extend_from_slice(&1u32.to_be_bytes());
+    stsd.extend_from_slice(&mjpg);
+    let stsd = full_box(b"stsd", 0, 0, &stsd);
+    empty_sample_tables(&stsd)
+}
+
+fn trak(
+    track_id: u32,
+    timescale: u32,
+    handler: &[u8; 4],
+    width: u16,
+    height: u16,
+    minf: &[u8],
+) -> Vec<u8> {
+    // tkhd
+    let mut tkhd = Vec::new();
+    tkhd.extend_from_slice(&0u32.to_be_bytes()); // creation
+    tkhd.extend_from_slice(&0u32.to_be_bytes()); // modification
+    tkhd.extend_from_slice(&track_id.to_be_bytes());
+    tkhd.extend_from_slice(&0u32.to_be_bytes()); // reserved
+    tkhd.extend_from_slice(&0u32.to_be_bytes()); // duration
+    tkhd.extend_from_slice(&[0u8; 8]); // reserved
+    tkhd.extend_from_slice(&0u16.to_be_bytes()); // layer
+    tkhd.extend_from_slice(&0u16.to_be_bytes()); // alternate_group
+    let volume: u16 = if handler == b"soun" { 0x0100 } else { 0 };
+    tkhd.extend_from_slice(&volume.to_be_bytes());
+    tkhd.extend_from_slice(&0u16.to_be_bytes()); // reserved
+    tkhd.extend_from_slice(&unity_matrix());
+    tkhd.extend_from_slice(&((width as u32) << 16).to_be_bytes());
+    tkhd.extend_from_slice(&((height as u32) << 16).to_be_bytes());
+    let tkhd = full_box(b"tkhd", 0, 3, &tkhd); // flags 3 = enabled + in movie
+
+    // mdia = mdhd + hdlr + minf
+    let mut mdhd = Vec::new();
+    mdhd.extend_from_slice(&0u32.to_be_bytes());
+    mdhd.extend_from_slice(&0u32.to_be_bytes());
+    mdhd.extend_from_slice(&timescale.to_be_bytes());
+    mdhd.extend_from_slice(&0u32.to_be_bytes()); // duration
+    mdhd.extend_from_slice(&0x55c4u16.to_be_bytes()); // language "und"
+    mdhd.extend_from_slice(&0u16.to_be_bytes()); // predefined
+    let mdhd = full_box(b"mdhd", 0, 0, &mdhd);
+
+    let mut hdlr = Vec::new();
+    hdlr.extend_from_slice(&0u32.to_be_bytes()); // predefined
+    hdlr.extend_from_slice(handler);
+    hdlr.extend_from_slice(&[0u8; 12]); // reserved
+    hdlr.extend_from_slice(b"recorder\0");
+    let hdlr = full_box(b"hdlr", 0, 0, &hdlr);
+
+    let mut mdia = Vec::new();
+    mdia.extend_from_slice(&mdhd);
+    mdia.extend_from_slice(&hdlr);
+    mdia.extend_from_slice(minf);
+    let mdia = mp4_box(b"mdia", &mdia);
+
+    let mut trak = Vec::new();
+    trak.extend_from_slice(&tkhd);
+    trak.extend_from_slice(&mdia);
+    mp4_box(b"trak", &trak)
+}
@@ -0,0 +1,40 @@
+use crate::config::AudioConfig;
+use crate::vad::save_recording;
+
+/// Continuous fixed-duration segmenter that runs alongside (or instead of) the
+/// VAD. It accumulates every chunk it is handed and, once a full segment's
+/// worth of samples has arrived, finalizes the current file and starts the next
+/// one on a continuous timeline so there are no gaps or overlaps at boundaries.
+pub struct SegmentRecorder {
+    config: AudioConfig,
+    buffer: Vec<i16>,
+    samples_per_segment: usize,
+}
+
+impl SegmentRecorder {
+    pub fn new(config: AudioConfig) -> Self {
+        let samples_per_segment =
+            (config.seconds_per_segment * config.target_sample_rate as f32).round() as usize;
+        SegmentRecorder {
+            config,
+            buffer: Vec::new(),
+            samples_per_segment: samples_per_segment.max(1),
+        }
+    }
+
+    /// Feed a chunk of PCM. Returns the path of a completed segment whenever the
+    /// accumulated audio reaches the configured segment length.
+    pub fn process_chunk(&mut self, chunk: &[i16]) -> Option<String> {
+        self.buffer.extend_from_slice(chunk);
+
+        if self.buffer.len() >= self.samples_per_segment {
+            let segment: Vec<i16> = self.buffer.drain(0..self.samples_per_segment).collect();
+            match save_recording(&segment, &self.config) {
+                Ok(path) => return Some(path),
+                Err(e) => eprintln!("Error saving segment: {}", e),
+            }
+        }
+
+        None
+    }
+}
@@ -0,0 +1,106 @@
+//! Incremental output sinks for live/streaming delivery.
+//!
+//! Unlike the file-on-disk recorders, a [`RecorderSink`] yields encoded buffers
+//! as they are produced so a caller can forward them over a socket instead of
+//! waiting for a completed file. [`FlvSink`] implements the trait for FLV, the
+//! classic container for live push.
+
+/// A streaming sink that turns coded audio/video samples into container bytes
+/// to be forwarded incrementally.
+pub trait RecorderSink {
+    /// Bytes that must precede any sample (e.g. the container header).
+    fn header(&mut self) -> Vec<u8>;
+
+    /// Wrap one audio sample. `is_sequence_header` marks the codec setup packet
+    /// (e.g. AAC `AudioSpecificConfig`) that must be sent before coded frames.
+    fn write_audio(&mut self, data: &[u8], timestamp_ms: u32, is_sequence_header: bool) -> Vec<u8>;
+
+    /// Wrap one video sample. `is_sequence_header` marks the codec setup packet
+    /// (e.g. the AVC sequence header) sent before coded frames.
+    fn write_video(
+        &mut self,
+        data: &[u8],
+        timestamp_ms: u32,
+        is_keyframe: bool,
+        is_sequence_header: bool,
+    ) -> Vec<u8>;
+}
+
+/// FLV stream writer: a 9-byte header followed by a sequence of tag records.
+pub struct FlvSink {
+    has_audio: bool,
+    has_video: bool,
+    header_written: bool,
+}
+
+impl FlvSink {
+    pub fn new(has_audio: bool, has_video: bool) -> Self {
+        Self {
+            has_audio,
+            has_video,
+            header_written: false,
+        }
+    }
+
+    /// Build one FLV tag: type, 24-bit data size, 24-bit timestamp + extended
+    /// byte, stream id 0, payload, then the 4-byte back-pointer.
+    fn tag(tag_type: u8, timestamp_ms: u32, payload: &[u8]) -> Vec<u8> {
+        let size = payload.len() as u32;
+        let mut out = Vec::with_capacity(payload.len() + 15);
+        out.push(tag_type);
+        out.extend_from_slice(&size.to_be_bytes()[1..]); // 24-bit data size
+        out.extend_from_slice(&timestamp_ms.to_be_bytes()[1..]); // lower 24 bits
+        out.push((timestamp_ms >> 24) as u8); // extended timestamp byte
+        out.extend_from_slice(&[0, 0, 0]); // stream id
+        out.extend_from_slice(payload);
+        let back_pointer = 11 + size;
+        out.extend_from_slice(&back_pointer.to_be_bytes());
+        out
+    }
+}
+
+impl RecorderSink for FlvSink {
+    fn header(&mut self) -> Vec<u8> {
+        self.header_written = true;
+        let mut flags = 0u8;
+        if self.has_audio {
+            flags |= 0x04;
+        }
+        if self.has_video {
+            flags |= 0x01;
+        }
+        let mut out = Vec::with_capacity(13);
+        out.extend_from_slice(b"FLV");
+        out.push(1); // version
+        out.push(flags);
+        out.extend_from_slice(&9u32.to_be_bytes()); // data offset (header size)
+        out.extend_from_slice(&0u32.to_be_bytes()); // PreviousTagSize0
+        out
+    }
+
+    fn write_audio(&mut self, data: &[u8], timestamp_ms: u32, is_sequence_header: bool) -> Vec<u8> {
+        // 0xAF = AAC, 44 kHz, 16-bit, stereo flags (AAC always carries its own).
+        let mut payload = Vec::with_capacity(data.len() + 2);
+        payload.push(0xAF);
+        payload.push(if is_sequence_header { 0 } else { 1 }); // AACPacketType
+        payload.extend_from_slice(data);
+        Self::tag(8, timestamp_ms, &payload)
+    }
+
+    fn write_video(
+        &mut self,
+        data: &[u8],
+        timestamp_ms: u32,
+        is_keyframe: bool,
+        is_sequence_header: bool,
+    ) -> Vec<u8> {
+        // frame_type<<4 | codec_id(7 = AVC). 1 = keyframe, 2 = inter frame.
+        let frame_type = if is_keyframe { 1u8 } else { 2u8 };
+        let mut payload = Vec::with_capacity(data.len() + 5);
+        payload.push((frame_type << 4) | 7);
+        payload.push(if is_sequence_header { 0 } else { 1 }); // AVCPacketType
+        payload.extend_from_slice(&[0, 0, 0]); // composition time offset
+        payload.extend_from_slice(data);
+        Self::tag(9, timestamp_ms, &payload)
+    }
+}
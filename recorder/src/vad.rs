@@ -15,6 +15,21 @@ pub struct VoiceActivityDetector {
     recording_buffer: Vec<i16>,
     pre_buffer: VecDeque<Vec<i16>>,
     chunk_duration_secs: f32,
+    /// Optional HDF5 session recorder fed the full PCM timeline and one event
+    /// per finalized utterance.
+    #[cfg(feature = "hdf5")]
+    session: Option<std::sync::Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>>,
+    /// Sender side of the PCM hand-off to the background writer thread spawned
+    /// in `set_session`; keeps the synchronous HDF5 disk I/O off the realtime
+    /// audio callback that calls `feed_session_pcm` every chunk.
+    #[cfg(feature = "hdf5")]
+    pcm_tx: Option<crossbeam_channel::Sender<Vec<i16>>>,
+    /// Total samples appended to the session so far (absolute sample offset).
+    #[cfg(feature = "hdf5")]
+    session_samples: usize,
+    /// Absolute sample offset at which the current utterance started.
+    #[cfg(feature = "hdf5")]
+    seg_start_sample: usize,
 }
 
 impl VoiceActivityDetector {
@@ -30,9 +45,30 @@ impl VoiceActivityDetector {
             recording_buffer: Vec::new(),
             pre_buffer: VecDeque::with_capacity(10),
             chunk_duration_secs,
+            #[cfg(feature = "hdf5")]
+            session: None,
+            #[cfg(feature = "hdf5")]
+            pcm_tx: None,
+            #[cfg(feature = "hdf5")]
+            session_samples: 0,
+            #[cfg(feature = "hdf5")]
+            seg_start_sample: 0,
         }
     }
 
+    /// Attach an HDF5 session recorder; the VAD then feeds it every chunk of PCM
+    /// and records an event for each finalized utterance. PCM is handed off to
+    /// a dedicated writer thread spawned here so the disk I/O in
+    /// `Hdf5Recorder::append_pcm` never runs on a realtime audio callback.
+    #[cfg(feature = "hdf5")]
+    pub fn set_session(
+        &mut self,
+        session: std::sync::Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>,
+    ) {
+        self.pcm_tx = Some(spawn_pcm_writer(session.clone()));
+        self.session = Some(session);
+    }
+
     fn calculate_rms(&self, samples: &[i16]) -> f32 {
         if samples.is_empty() {
             return 0.0;
@@ -49,14 +85,34 @@ impl VoiceActivityDetector {
         (sum_squares / samples.len() as f64).sqrt() as f32
     }
 
-    pub fn process_chunk(&mut self, chunk: Vec<i16>) -> Option<String> {
-        let volume = self.calculate_rms(&chunk);
+    /// Feed one chunk of PCM into the attached HDF5 session's timeline. Unlike
+    /// [`Self::process_chunk`] this must run for every captured chunk
+    /// regardless of recording mode, or the session's `pcm` dataset (and the
+    /// sample offsets events are timestamped against) silently misses audio
+    /// whenever VAD-triggered capture isn't the active mode. No-op without an
+    /// attached session.
+    pub fn feed_session_pcm(&mut self, chunk: &[i16]) {
+        #[cfg(feature = "hdf5")]
+        {
+            if let Some(tx) = &self.pcm_tx {
+                let _ = tx.send(chunk.to_vec());
+            }
+            self.session_samples += chunk.len();
+        }
+        #[cfg(not(feature = "hdf5"))]
+        {
+            let _ = chunk;
+        }
+    }
+
+    pub fn process_chunk(&mut self, chunk: &[i16]) -> Option<String> {
+        let volume = self.calculate_rms(chunk);
 
         if !self.is_active {
             self.background_level = self.config.background_alpha * self.background_level
                 + (1.0 - self.config.background_alpha) * volume;
 
-            self.pre_buffer.push_back(chunk.clone());
+            self.pre_buffer.push_back(chunk.to_vec());
             if self.pre_buffer.len() > 10 {
                 self.pre_buffer.pop_front();
             }
@@ -66,12 +122,12 @@ impl VoiceActivityDetector {
                 for buffered_chunk in &self.pre_buffer {
                     self.recording_buffer.extend_from_slice(buffered_chunk);
                 }
-                self.recording_buffer.extend_from_slice(&chunk);
+                self.recording_buffer.extend_from_slice(chunk);
             }
 
             None
         } else {
-            self.recording_buffer.extend_from_slice(&chunk);
+            self.recording_buffer.extend_from_slice(chunk);
 
             if volume > self.peak_volume {
                 self.peak_volume = volume;
@@ -107,6 +163,14 @@ impl VoiceActivityDetector {
         self.recording_buffer.clear();
         self.silent_duration = 0.0;
         self.peak_volume = initial_volume;
+        // The utterance begins at the pre-buffered chunks that are about to be
+        // copied into `recording_buffer`, so rewind the session offset by their
+        // length.
+        #[cfg(feature = "hdf5")]
+        {
+            let prebuf: usize = self.pre_buffer.iter().map(|c| c.len()).sum();
+            self.seg_start_sample = self.session_samples.saturating_sub(prebuf);
+        }
         if !Self::is_llm_busy() {
             println!("\u{2139}\u{FE0F} Recording started (vol={:.4})", initial_volume);
         }
@@ -122,6 +186,17 @@ impl VoiceActivityDetector {
             println!("\u{2139}\u{FE0F} Recording stopped (duration: {:.1}s)", duration);
         }
 
+        // Record the segment's sample range (and, inside the recorder, the
+        // frames that fall within it) in the session container.
+        #[cfg(feature = "hdf5")]
+        {
+            if let Some(session) = &self.session {
+                if let Ok(mut s) = session.lock() {
+                    let _ = s.append_event(self.seg_start_sample, self.session_samples);
+                }
+            }
+        }
+
         let filepath = match self.save_audio_file() {
             Ok(path) => path,
             Err(e) => {
@@ -136,84 +211,281 @@ impl VoiceActivityDetector {
     }
 
     fn save_audio_file(&self) -> Result<String> {
-        fs::create_dir_all(&self.config.output_directory)?;
-
-        let timestamp = chrono::Local::now().format("%y%m%d_%H%M%S").to_string();
-        let ext = match self.config.format {
-            AudioFormat::Flac => "flac",
-            AudioFormat::Wav => "wav",
-        };
-        let filename = format!("{}.{}", timestamp, ext);
-        let filepath = PathBuf::from(&self.config.output_directory).join(&filename);
+        save_recording(&self.recording_buffer, &self.config)
+    }
 
-        match self.config.format {
-            AudioFormat::Flac => self.save_flac(&filepath)?,
-            AudioFormat::Wav => self.save_wav(&filepath)?,
-        }
+    fn reset_state(&mut self) {
+        self.is_active = false;
+        self.recording_buffer.clear();
+        self.silent_duration = 0.0;
+        self.peak_volume = 0.0;
+    }
+}
 
-        Ok(filepath.to_string_lossy().to_string())
+/// Monotonic suffix disambiguating files whose 1-second-resolution timestamp
+/// collides, e.g. a `RecordingMode::Both` continuous-segment rotation and a
+/// VAD finalize landing in the same wall-clock second on the same audio
+/// thread. `File::create` doesn't error on an existing path, so without this
+/// the second writer would silently truncate the first's output.
+static SAVE_COUNTER: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
+
+/// Write a PCM buffer to a timestamped file in the configured format and return
+/// its path. Shared by the VAD path and the continuous [`SegmentRecorder`].
+pub(crate) fn save_recording(buffer: &[i16], config: &AudioConfig) -> Result<String> {
+    fs::create_dir_all(&config.output_directory)?;
+
+    let timestamp = chrono::Local::now().format("%y%m%d_%H%M%S").to_string();
+    let seq = SAVE_COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+    let ext = match config.format {
+        AudioFormat::Flac => "flac",
+        AudioFormat::Wav => "wav",
+        // The HDF5 session backend owns the `.h5` container; the per-utterance
+        // path falls back to WAV so a segment is still written when the session
+        // recorder is not active.
+        AudioFormat::Hdf5 => "wav",
+        AudioFormat::Aac => "aac",
+        AudioFormat::Opus => "opus",
+        AudioFormat::Mp3 => "mp3",
+    };
+    let filename = format!("{}_{:06}.{}", timestamp, seq, ext);
+    let filepath = PathBuf::from(&config.output_directory).join(&filename);
+
+    match config.format {
+        AudioFormat::Flac => save_flac(buffer, config, &filepath)?,
+        AudioFormat::Wav | AudioFormat::Hdf5 => save_wav(buffer, config, &filepath)?,
+        AudioFormat::Aac => save_aac(buffer, config, &filepath)?,
+        AudioFormat::Opus => save_opus(buffer, config, &filepath)?,
+        AudioFormat::Mp3 => save_mp3(buffer, config, &filepath)?,
     }
 
-    fn save_flac(&self, filepath: &PathBuf) -> Result<()> {
-        use flacenc::bitsink::ByteSink;
-        use flacenc::component::BitRepr;
-        use flacenc::config::Encoder as FlacConfig;
-        use flacenc::source::MemSource;
+    Ok(filepath.to_string_lossy().to_string())
+}
 
-        let samples: Vec<i32> = self.recording_buffer.iter().map(|&s| s as i32).collect();
-        let source = MemSource::from_samples(&samples, 1, 16, self.config.target_sample_rate as usize);
+fn save_flac(buffer: &[i16], config: &AudioConfig, filepath: &PathBuf) -> Result<()> {
+    use flacenc::bitsink::ByteSink;
+    use flacenc::component::BitRepr;
+    use flacenc::config::Encoder as FlacConfig;
+    use flacenc::source::MemSource;
 
-        let flac_config = FlacConfig::default()
-            .into_verified()
-            .map_err(|e| anyhow::anyhow!("Invalid FLAC config: {:?}", e))?;
+    let samples: Vec<i32> = buffer.iter().map(|&s| s as i32).collect();
+    let source = MemSource::from_samples(&samples, 1, 16, config.target_sample_rate as usize);
 
-        let stream = flacenc::encode_with_fixed_block_size(&flac_config, source, 4096)
-            .map_err(|e| anyhow::anyhow!("FLAC encoding failed: {:?}", e))?;
+    let flac_config = FlacConfig::default()
+        .into_verified()
+        .map_err(|e| anyhow::anyhow!("Invalid FLAC config: {:?}", e))?;
 
-        let mut sink = ByteSink::new();
-        stream
-            .write(&mut sink)
-            .map_err(|e| anyhow::anyhow!("FLAC write failed: {:?}", e))?;
+    let stream = flacenc::encode_with_fixed_block_size(&flac_config, source, 4096)
+        .map_err(|e| anyhow::anyhow!("FLAC encoding failed: {:?}", e))?;
 
-        fs::write(filepath, sink.as_slice())?;
-        Ok(())
-    }
+    let mut sink = ByteSink::new();
+    stream
+        .write(&mut sink)
+        .map_err(|e| anyhow::anyhow!("FLAC write failed: {:?}", e))?;
 
-    fn save_wav(&self, filepath: &PathBuf) -> Result<()> {
-        let file = File::create(filepath)?;
-        let mut writer = BufWriter::new(file);
+    fs::write(filepath, sink.as_slice())?;
+    Ok(())
+}
+
+fn save_wav(buffer: &[i16], config: &AudioConfig, filepath: &PathBuf) -> Result<()> {
+    let file = File::create(filepath)?;
+    let mut writer = BufWriter::new(file);
+
+    let num_samples = buffer.len() as u32;
+    let byte_rate = config.target_sample_rate * 2;
+    let data_size = num_samples * 2;
+    let file_size = 36 + data_size;
+
+    use std::io::Write;
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&file_size.to_le_bytes())?;
+    writer.write_all(b"WAVE")?;
+    writer.write_all(b"fmt ")?;
+    writer.write_all(&16u32.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&1u16.to_le_bytes())?;
+    writer.write_all(&config.target_sample_rate.to_le_bytes())?;
+    writer.write_all(&byte_rate.to_le_bytes())?;
+    writer.write_all(&2u16.to_le_bytes())?;
+    writer.write_all(&16u16.to_le_bytes())?;
+    writer.write_all(b"data")?;
+    writer.write_all(&data_size.to_le_bytes())?;
+
+    for &sample in buffer {
+        writer.write_all(&sample.to_le_bytes())?;
+    }
 
-        let num_samples = self.recording_buffer.len() as u32;
-        let byte_rate = self.config.target_sample_rate * 2;
-        let data_size = num_samples * 2;
-        let file_size = 36 + data_size;
+    Ok(())
+}
 
-        use std::io::Write;
-        writer.write_all(b"RIFF")?;
-        writer.write_all(&file_size.to_le_bytes())?;
-        writer.write_all(b"WAVE")?;
-        writer.write_all(b"fmt ")?;
-        writer.write_all(&16u32.to_le_bytes())?;
-        writer.write_all(&1u16.to_le_bytes())?;
-        writer.write_all(&1u16.to_le_bytes())?;
-        writer.write_all(&self.config.target_sample_rate.to_le_bytes())?;
-        writer.write_all(&byte_rate.to_le_bytes())?;
-        writer.write_all(&2u16.to_le_bytes())?;
-        writer.write_all(&16u16.to_le_bytes())?;
-        writer.write_all(b"data")?;
-        writer.write_all(&data_size.to_le_bytes())?;
+/// Encode the take to self-framed ADTS AAC via `fdk-aac`, feeding the encoder
+/// its native 1024-sample frames and flushing the tail on stop.
+fn save_aac(buffer: &[i16], config: &AudioConfig, filepath: &PathBuf) -> Result<()> {
+    use fdk_aac::enc::{ChannelMode, Encoder, EncoderParams, Transport};
+    use std::io::Write;
+
+    let encoder = Encoder::new(EncoderParams {
+        bit_rate: config.aac_bitrate_kbps * 1000,
+        sample_rate: config.target_sample_rate,
+        transport: Transport::Adts,
+        channels: ChannelMode::Mono,
+    })
+    .map_err(|e| anyhow::anyhow!("AAC encoder init failed: {:?}", e))?;
+
+    let file = File::create(filepath)?;
+    let mut writer = BufWriter::new(file);
+    let mut out = vec![0u8; 4096];
+
+    // Feed decoder-native frames of 1024 samples and stream each packet to disk.
+    for frame in buffer.chunks(1024) {
+        let info = encoder
+            .encode(frame, &mut out)
+            .map_err(|e| anyhow::anyhow!("AAC encode failed: {:?}", e))?;
+        writer.write_all(&out[..info.output_size])?;
+    }
 
-        for &sample in &self.recording_buffer {
-            writer.write_all(&sample.to_le_bytes())?;
+    // Flush the encoder's tail by draining with empty input.
+    loop {
+        let info = encoder
+            .encode(&[], &mut out)
+            .map_err(|e| anyhow::anyhow!("AAC flush failed: {:?}", e))?;
+        if info.output_size == 0 {
+            break;
         }
+        writer.write_all(&out[..info.output_size])?;
+    }
 
-        Ok(())
+    Ok(())
+}
+
+/// Encode the take to a standard Ogg-Opus file: an `OpusHead`/`OpusTags` header
+/// pair followed by 20 ms audio packets, streamed to disk a page at a time.
+fn save_opus(buffer: &[i16], config: &AudioConfig, filepath: &PathBuf) -> Result<()> {
+    use ogg::{PacketWriteEndInfo, PacketWriter};
+    use opus::{Application, Channels, Encoder as OpusEncoder};
+
+    let rate = config.target_sample_rate;
+    let mut encoder = OpusEncoder::new(rate, Channels::Mono, Application::Voip)
+        .map_err(|e| anyhow::anyhow!("Opus encoder init failed: {:?}", e))?;
+    encoder
+        .set_bitrate(opus::Bitrate::Bits((config.opus_bitrate_kbps * 1000) as i32))
+        .map_err(|e| anyhow::anyhow!("Opus bitrate set failed: {:?}", e))?;
+
+    let file = File::create(filepath)?;
+    let mut writer = PacketWriter::new(BufWriter::new(file));
+    let serial = 1u32;
+
+    // OpusHead identification header (RFC 7845).
+    let mut head = Vec::new();
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(1); // channel count
+    head.extend_from_slice(&0u16.to_le_bytes()); // pre-skip
+    head.extend_from_slice(&rate.to_le_bytes()); // input sample rate
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family
+    writer
+        .write_packet(head.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)
+        .map_err(|e| anyhow::anyhow!("Opus header write failed: {:?}", e))?;
+
+    // OpusTags comment header with a vendor string and no user comments.
+    let mut tags = Vec::new();
+    tags.extend_from_slice(b"OpusTags");
+    let vendor = b"recorder";
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // user comment list length
+    writer
+        .write_packet(tags.into_boxed_slice(), serial, PacketWriteEndInfo::EndPage, 0)
+        .map_err(|e| anyhow::anyhow!("Opus tags write failed: {:?}", e))?;
+
+    // 20 ms audio frames; the granule position counts decoded samples at a
+    // fixed 48kHz timebase per RFC 7845, regardless of the encoder's actual
+    // sample rate, so scale each frame's contribution accordingly.
+    let frame_len = (rate as usize / 50).max(1);
+    let mut granule = 0u64;
+    let total = buffer.len() / frame_len;
+    for (i, frame) in buffer.chunks(frame_len).enumerate() {
+        if frame.len() < frame_len {
+            break; // drop a trailing partial frame Opus can't encode
+        }
+        let packet = encoder
+            .encode_vec(frame, 4000)
+            .map_err(|e| anyhow::anyhow!("Opus encode failed: {:?}", e))?;
+        granule += frame.len() as u64 * 48_000 / rate as u64;
+        let end = if i + 1 >= total {
+            PacketWriteEndInfo::EndStream
+        } else {
+            PacketWriteEndInfo::NormalPacket
+        };
+        writer
+            .write_packet(packet.into_boxed_slice(), serial, end, granule)
+            .map_err(|e| anyhow::anyhow!("Opus packet write failed: {:?}", e))?;
     }
 
-    fn reset_state(&mut self) {
-        self.is_active = false;
-        self.recording_buffer.clear();
-        self.silent_duration = 0.0;
-        self.peak_volume = 0.0;
+    Ok(())
+}
+
+/// Encode the take to MP3, streaming encoded frames to disk incrementally.
+fn save_mp3(buffer: &[i16], config: &AudioConfig, filepath: &PathBuf) -> Result<()> {
+    use mp3lame_encoder::{Builder, FlushNoGap, MonoPcm};
+    use std::io::Write;
+
+    let mut builder = Builder::new().ok_or_else(|| anyhow::anyhow!("MP3 builder init failed"))?;
+    builder
+        .set_num_channels(1)
+        .map_err(|e| anyhow::anyhow!("MP3 channels failed: {:?}", e))?;
+    builder
+        .set_sample_rate(config.target_sample_rate)
+        .map_err(|e| anyhow::anyhow!("MP3 sample rate failed: {:?}", e))?;
+    builder
+        .set_brate(mp3lame_encoder::Bitrate::Kbps(config.mp3_bitrate_kbps as u16 as usize))
+        .map_err(|e| anyhow::anyhow!("MP3 bitrate failed: {:?}", e))?;
+    let mut encoder = builder
+        .build()
+        .map_err(|e| anyhow::anyhow!("MP3 encoder build failed: {:?}", e))?;
+
+    let file = File::create(filepath)?;
+    let mut writer = BufWriter::new(file);
+
+    // Encode one MPEG frame's worth (1152 samples) at a time and write each
+    // block out as it is produced. The buffer is sized to LAME's documented
+    // worst-case output bound for a block of PCM samples.
+    let mut mp3_buf = Vec::with_capacity(1152 * 5 / 4 + 7200);
+    for block in buffer.chunks(1152) {
+        mp3_buf.clear();
+        let encoded = encoder
+            .encode(MonoPcm(block), mp3_buf.spare_capacity_mut())
+            .map_err(|e| anyhow::anyhow!("MP3 encode failed: {:?}", e))?;
+        unsafe { mp3_buf.set_len(encoded) };
+        writer.write_all(&mp3_buf)?;
     }
+
+    let mut tail = Vec::with_capacity(7200);
+    let flushed = encoder
+        .flush::<FlushNoGap>(tail.spare_capacity_mut())
+        .map_err(|e| anyhow::anyhow!("MP3 flush failed: {:?}", e))?;
+    unsafe { tail.set_len(flushed) };
+    writer.write_all(&tail)?;
+
+    Ok(())
+}
+
+/// Spawn the background thread that owns all PCM writes to a session once
+/// attached, draining chunks handed off by `feed_session_pcm` so the
+/// `dataset.resize` + `write_slice` disk I/O never blocks the realtime audio
+/// callback. The thread exits once every sender (i.e. every VAD instance
+/// feeding this session) is dropped and the channel disconnects.
+#[cfg(feature = "hdf5")]
+fn spawn_pcm_writer(
+    session: std::sync::Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>,
+) -> crossbeam_channel::Sender<Vec<i16>> {
+    let (tx, rx) = crossbeam_channel::unbounded::<Vec<i16>>();
+    std::thread::spawn(move || {
+        while let Ok(chunk) = rx.recv() {
+            if let Ok(mut s) = session.lock() {
+                let _ = s.append_pcm(&chunk);
+            }
+        }
+    });
+    tx
 }
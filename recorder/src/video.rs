@@ -11,6 +11,11 @@ pub struct VideoRecorder {
     frame_buffer: Arc<FrameBuffer>,
     capture_thread: Option<thread::JoinHandle<()>>,
     config: VideoConfig,
+    /// Optional container muxer fed every stored frame as a video sample.
+    muxer: Option<Arc<std::sync::Mutex<crate::muxer::Fmp4Muxer>>>,
+    /// Optional HDF5 session recorder fed every stored frame.
+    #[cfg(feature = "hdf5")]
+    session: Option<Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>>,
 }
 
 impl VideoRecorder {
@@ -25,9 +30,28 @@ impl VideoRecorder {
             frame_buffer,
             capture_thread: None,
             config,
+            muxer: None,
+            #[cfg(feature = "hdf5")]
+            session: None,
         })
     }
 
+    /// Attach a container muxer so each stored frame is also written as a video
+    /// sample to the muxed output.
+    pub fn set_muxer(&mut self, muxer: Arc<std::sync::Mutex<crate::muxer::Fmp4Muxer>>) {
+        self.muxer = Some(muxer);
+    }
+
+    /// Attach an HDF5 session recorder so each stored frame is also written to
+    /// the session container.
+    #[cfg(feature = "hdf5")]
+    pub fn set_session(
+        &mut self,
+        session: Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>,
+    ) {
+        self.session = Some(session);
+    }
+
     pub fn start(&mut self) -> Result<()> {
         if self.running.load(Ordering::SeqCst) {
             return Ok(());
@@ -38,9 +62,19 @@ impl VideoRecorder {
         let running = Arc::clone(&self.running);
         let frame_buffer = Arc::clone(&self.frame_buffer);
         let config = self.config.clone();
+        let muxer = self.muxer.clone();
+        #[cfg(feature = "hdf5")]
+        let session = self.session.clone();
 
         let handle = thread::spawn(move || {
-            if let Err(e) = capture_loop(running, frame_buffer, config) {
+            if let Err(e) = capture_loop(
+                running,
+                frame_buffer,
+                config,
+                muxer,
+                #[cfg(feature = "hdf5")]
+                session,
+            ) {
                 eprintln!("Video capture error: {}", e);
             }
         });
@@ -63,10 +97,66 @@ impl VideoRecorder {
         self.frame_buffer.get_frames_since(duration_secs)
     }
 
+    pub fn get_keyframes_for_duration(&self, duration_secs: f32) -> Vec<Vec<u8>> {
+        self.frame_buffer.get_keyframes_since(duration_secs)
+    }
+
     pub fn get_latest_frame(&self) -> Option<Vec<u8>> {
         self.frame_buffer.get_latest()
     }
 
+    /// Export the frames covering the requested window and return the written
+    /// path. MJPEG frames are wrapped in a playable AVI; H.264/AV1 access units
+    /// are concatenated into their raw elementary stream, since the MJPEG AVI
+    /// container can only carry JPEG payloads.
+    pub fn export_clip(
+        &self,
+        start_offset_secs: f32,
+        duration_secs: f32,
+        output_directory: &str,
+    ) -> Result<String> {
+        use crate::config::VideoCodec;
+
+        let frames = self
+            .frame_buffer
+            .frames_in_window(start_offset_secs, duration_secs);
+
+        std::fs::create_dir_all(output_directory)?;
+        let timestamp = chrono::Local::now().format("%y%m%d_%H%M%S").to_string();
+        let dir = std::path::PathBuf::from(output_directory);
+
+        // Without the `codecs` feature the encoder falls back to MJPEG, so the
+        // buffered frames are JPEGs whatever codec was configured.
+        let codec = if cfg!(feature = "codecs") {
+            self.config.video_codec.clone()
+        } else {
+            VideoCodec::Mjpeg
+        };
+
+        match codec {
+            VideoCodec::Mjpeg => {
+                let path = dir.join(format!("{}.avi", timestamp));
+                crate::clip::write_mjpeg_avi(&frames, self.config.width, self.config.height, &path)
+            }
+            VideoCodec::H264 | VideoCodec::Av1 => {
+                if frames.is_empty() {
+                    return Err(anyhow::anyhow!("No frames in the requested window"));
+                }
+                let ext = match codec {
+                    VideoCodec::H264 => "h264",
+                    _ => "obu",
+                };
+                let path = dir.join(format!("{}.{}", timestamp, ext));
+                let mut stream = Vec::new();
+                for (_, data) in &frames {
+                    stream.extend_from_slice(data);
+                }
+                std::fs::write(&path, &stream)?;
+                Ok(path.to_string_lossy().to_string())
+            }
+        }
+    }
+
     pub fn stats(&self) -> (usize, f32) {
         self.frame_buffer.stats()
     }
@@ -82,6 +172,10 @@ fn capture_loop(
     running: Arc<AtomicBool>,
     frame_buffer: Arc<FrameBuffer>,
     config: VideoConfig,
+    muxer: Option<Arc<std::sync::Mutex<crate::muxer::Fmp4Muxer>>>,
+    #[cfg(feature = "hdf5")] session: Option<
+        Arc<std::sync::Mutex<crate::hdf5_recorder::Hdf5Recorder>>,
+    >,
 ) -> Result<()> {
     use nokhwa::pixel_format::RgbFormat;
     use nokhwa::utils::{CameraFormat, CameraIndex, FrameFormat, RequestedFormat, RequestedFormatType, Resolution};
@@ -97,8 +191,19 @@ fn capture_loop(
     let mut camera = Camera::new(index, requested)?;
     camera.open_stream()?;
 
+    let mut encoder = crate::video_encoder::VideoEncoder::new(&config)?;
+
     let target_interval = Duration::from_secs_f32(1.0 / config.fps);
+    let keyframe_interval = Duration::from_secs_f32(config.keyframe_max_interval_secs);
     let mut last_capture = Instant::now() - target_interval;
+    let mut last_keyframe = Instant::now() - keyframe_interval;
+    // Wall-clock time of the last frame actually handed to the muxer. The
+    // scene-change detector means kept frames can be far more than
+    // `1/fps` apart, so the muxer's per-sample duration must track real
+    // elapsed time rather than a constant derived from `fps`, or the video
+    // track's timeline drifts behind the audio track's.
+    let mut last_muxer_write = Instant::now() - target_interval;
+    let mut prev_luma: Option<Vec<f32>> = None;
 
     while running.load(Ordering::SeqCst) {
         match camera.frame() {
@@ -108,10 +213,58 @@ fn capture_loop(
                     let rgb_data = frame.decode_image::<RgbFormat>().ok();
 
                     if let Some(rgb) = rgb_data {
-                        if let Ok(jpeg_data) = encode_jpeg(&rgb, config.width, config.height, config.jpeg_quality) {
-                            frame_buffer.push(jpeg_data);
-                            last_capture = now;
+                        // Cheap scene-change test on a downscaled luma grid before
+                        // the expensive JPEG encode, so a static scene doesn't fill
+                        // the ring buffer with near-identical frames.
+                        let luma = downscale_luma(&rgb, config.width, config.height);
+                        let score = prev_luma
+                            .as_ref()
+                            .map(|prev| mean_abs_diff(prev, &luma))
+                            .unwrap_or(f32::INFINITY);
+
+                        let periodic = now.duration_since(last_keyframe) >= keyframe_interval;
+                        let scene_change = score >= config.scene_change_threshold;
+
+                        if periodic || scene_change {
+                            match encoder.encode(&rgb, config.width, config.height) {
+                                Ok(coded) if !coded.is_empty() => {
+                                    #[cfg(feature = "hdf5")]
+                                    if let Some(session) = &session {
+                                        if let Ok(mut s) = session.lock() {
+                                            let _ = s.append_frame(&coded);
+                                        }
+                                    }
+                                    if let Some(muxer) = muxer.as_ref() {
+                                        let elapsed_ms =
+                                            now.duration_since(last_muxer_write).as_millis() as u32;
+                                        if let Ok(mut muxer) = muxer.lock() {
+                                            if let Err(e) = muxer.write_sample(
+                                                crate::muxer::VIDEO_TRACK_ID,
+                                                &coded,
+                                                elapsed_ms,
+                                                periodic || scene_change,
+                                            ) {
+                                                eprintln!("Muxer video write failed: {}", e);
+                                            }
+                                        }
+                                        last_muxer_write = now;
+                                    }
+                                    // Every frame we store is a distinct/kept
+                                    // frame (periodic anchor or scene change), so
+                                    // all of them are keyframes for the purpose of
+                                    // get_keyframes_since.
+                                    frame_buffer.push(coded, periodic || scene_change, score);
+                                    if periodic {
+                                        last_keyframe = now;
+                                    }
+                                }
+                                Ok(_) => {}
+                                Err(e) => eprintln!("Frame encode error: {}", e),
+                            }
                         }
+
+                        prev_luma = Some(luma);
+                        last_capture = now;
                     }
                 }
             }
@@ -126,14 +279,53 @@ fn capture_loop(
     Ok(())
 }
 
-fn encode_jpeg(rgb_data: &[u8], width: u32, height: u32, quality: u8) -> Result<Vec<u8>> {
-    use jpeg_encoder::{ColorType, Encoder};
+/// Side of the square luma grid the scene-change detector works on.
+const LUMA_GRID: usize = 32;
 
-    let mut output = Vec::new();
-    let encoder = Encoder::new(&mut output, quality);
-    encoder
-        .encode(rgb_data, width as u16, height as u16, ColorType::Rgb)
-        .map_err(|e| anyhow::anyhow!("JPEG encoding failed: {:?}", e))?;
+/// Average an interleaved RGB frame down to a fixed `LUMA_GRID`×`LUMA_GRID`
+/// luma image. Each cell is the mean luma (Rec. 601 weights) of the block of
+/// source pixels that maps to it, giving a cheap, resolution-independent
+/// fingerprint for frame-difference comparison.
+fn downscale_luma(rgb_data: &[u8], width: u32, height: u32) -> Vec<f32> {
+    let width = width as usize;
+    let height = height as usize;
+    let mut grid = vec![0.0f32; LUMA_GRID * LUMA_GRID];
+    if width == 0 || height == 0 {
+        return grid;
+    }
 
-    Ok(output)
+    let mut counts = vec![0u32; LUMA_GRID * LUMA_GRID];
+    for y in 0..height {
+        let gy = y * LUMA_GRID / height;
+        for x in 0..width {
+            let gx = x * LUMA_GRID / width;
+            let p = (y * width + x) * 3;
+            if p + 2 >= rgb_data.len() {
+                continue;
+            }
+            let luma = 0.299 * rgb_data[p] as f32
+                + 0.587 * rgb_data[p + 1] as f32
+                + 0.114 * rgb_data[p + 2] as f32;
+            let cell = gy * LUMA_GRID + gx;
+            grid[cell] += luma;
+            counts[cell] += 1;
+        }
+    }
+
+    for (cell, count) in grid.iter_mut().zip(counts.iter()) {
+        if *count > 0 {
+            *cell /= *count as f32;
+        }
+    }
+    grid
+}
+
+/// Mean absolute per-cell difference between two downscaled luma grids, in the
+/// 0..255 luma scale.
+fn mean_abs_diff(a: &[f32], b: &[f32]) -> f32 {
+    if a.is_empty() || a.len() != b.len() {
+        return f32::INFINITY;
+    }
+    let sum: f32 = a.iter().zip(b.iter()).map(|(x, y)| (x - y).abs()).sum();
+    sum / a.len() as f32
 }